@@ -0,0 +1,199 @@
+
+use super::{io, Write, Operation, App, Arg, ArgMatches, SubCommand, Path, PathBuf, File,
+            OpenOptions, Format, Quality, Comp, Decomp};
+use super::valid::valid_path;
+use super::tarball::{self, Members};
+use super::staging;
+use super::create::{self, get_options, get_items, Options};
+
+use std::io::{BufReader, Seek, SeekFrom};
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::time::UNIX_EPOCH;
+
+/// Build the `append` subcommand
+pub fn build<'a>() -> App<'static, 'a> {
+    SubCommand::with_name("append")
+        .about("Add files to the end of an existing archive")
+        .arg(
+            Arg::with_name("archive")
+                .index(1)
+                .takes_value(true)
+                .multiple(false)
+                .value_name("ARCHIVE")
+                .required(true)
+                .validator(valid_path)
+                .next_line_help(true)
+                .help("archive to extend"),
+        )
+        .arg(
+            Arg::with_name("file")
+                .short("f")
+                .long("file")
+                .takes_value(true)
+                .multiple(true)
+                .value_name("FILE/DIR")
+                .required_unless("files-from")
+                .next_line_help(true)
+                .help("what to add"),
+        )
+}
+
+pub fn get(x: &ArgMatches) -> Operation {
+    Operation::Append(
+        PathBuf::from(x.value_of("archive").unwrap()),
+        get_items(x),
+        get_options(x),
+    )
+}
+
+/// Append `items` to `archive`
+pub fn exec(archive: &Path, items: &[PathBuf], opts: &Options) -> io::Result<()> {
+    extend(archive, items, opts, false)
+}
+
+/// Add `items` to the end of `archive`
+///
+/// Plain tarballs are extended in place. Compressed archives are decoded,
+/// stripped of their end-of-archive marker, and re-encoded in the same
+/// format next to the original before being renamed over it.
+///
+/// When `newer` is set only files which are missing from the archive, or
+/// were modified after their archived copy, are added.
+pub fn extend(archive: &Path, items: &[PathBuf], opts: &Options, newer: bool) -> io::Result<()> {
+    match Format::from_path(archive)? {
+        Format::Tar(q) => in_place(archive, q, items, opts, newer),
+        f => reencode(archive, f, items, opts, newer),
+    }
+}
+
+/// Write new members over the end-of-archive marker
+///
+/// If adding them fails the marker is put back after the old members, so
+/// the archive still reads as it did before.
+fn in_place(
+    archive: &Path,
+    q: Quality,
+    items: &[PathBuf],
+    opts: &Options,
+    newer: bool,
+) -> io::Result<()> {
+    let mut f = OpenOptions::new().read(true).write(true).open(archive)?;
+    let own = [file_id(&f.metadata()?)];
+    let mut seen = HashMap::new();
+    let end = {
+        let mut members = Members::new(BufReader::with_capacity(131072, &mut f));
+        while let Option::Some(m) = members.next_member()? {
+            if newer {
+                seen.insert(create::normalize_name(&m.path), m.header.mtime()?);
+            }
+        }
+        members.end()
+    };
+    f.seek(SeekFrom::Start(end))?;
+    let mut dup = f.try_clone()?;
+    match add(f, q, items, opts, |p, m| !is_own(&own, m) && is_newer(&seen, p, m)) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = restore(&mut dup, end);
+            Err(e)
+        }
+    }
+}
+
+/// Add `items` where `f` is and end the archive there
+fn add<F>(f: File, q: Quality, items: &[PathBuf], opts: &Options, keep: F) -> io::Result<()>
+where
+    F: Fn(&Path, &Metadata) -> bool,
+{
+    let comp = Comp::from_format(Format::Tar(q), f)?;
+    let comp = create::building_filtered(comp, items, opts, keep)?;
+    let mut f = tarball::finish(comp)?;
+    // anything after the new marker is left over from the old one
+    let len = f.seek(SeekFrom::Current(0))?;
+    f.set_len(len)?;
+    f.sync_all()
+}
+
+/// Write the end-of-archive marker back after the first `end` bytes
+fn restore(f: &mut File, end: u64) -> io::Result<()> {
+    f.seek(SeekFrom::Start(end))?;
+    f.write_all(&[0; 1024])?;
+    f.set_len(end + 1024)?;
+    f.sync_all()
+}
+
+fn reencode(
+    archive: &Path,
+    f: Format,
+    items: &[PathBuf],
+    opts: &Options,
+    newer: bool,
+) -> io::Result<()> {
+    let tmp = staging::sibling(archive);
+    match rewrite(archive, &tmp, f, items, opts, newer) {
+        Ok(()) => staging::commit(&tmp, archive),
+        Err(e) => {
+            staging::discard(&tmp);
+            Err(e)
+        }
+    }
+}
+
+/// Copy every existing member into `tmp` then add the new ones
+fn rewrite(
+    archive: &Path,
+    tmp: &Path,
+    f: Format,
+    items: &[PathBuf],
+    opts: &Options,
+    newer: bool,
+) -> io::Result<()> {
+    let src = File::open(archive)?;
+    let dst = File::create(tmp)?;
+    let own = [file_id(&src.metadata()?), file_id(&dst.metadata()?)];
+    let mut members = Members::new(Decomp::from_unknown(src)?);
+    let mut comp = create::new_comp(f, dst, opts)?;
+    let mut seen = HashMap::new();
+    while let Option::Some(m) = members.next_member()? {
+        if newer {
+            seen.insert(create::normalize_name(&m.path), m.header.mtime()?);
+        }
+        comp.write_all(&m.raw)?;
+        members.copy_data(&mut comp)?;
+    }
+    let comp = create::building_filtered(comp, items, opts, |p, m| {
+        !is_own(&own, m) && is_newer(&seen, p, m)
+    })?;
+    tarball::finish(comp)?.sync_all()
+}
+
+/// Is the file on disk absent from the archive, or newer than its archived
+/// copy. Later members shadow earlier ones, just as they do on extraction.
+fn is_newer(seen: &HashMap<PathBuf, u64>, name: &Path, meta: &Metadata) -> bool {
+    let archived = match seen.get(name) {
+        Option::None => return true,
+        Option::Some(x) => *x,
+    };
+    match meta.modified().map(|t| t.duration_since(UNIX_EPOCH)) {
+        Ok(Ok(d)) => d.as_secs() > archived,
+        _ => true,
+    }
+}
+
+/// Is this the archive being written, which must not be added to itself
+fn is_own(own: &[Option<(u64, u64)>], meta: &Metadata) -> bool {
+    let id = file_id(meta);
+    id.is_some() && own.contains(&id)
+}
+
+#[cfg(unix)]
+fn file_id(meta: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(windows)]
+fn file_id(_: &Metadata) -> Option<(u64, u64)> {
+    None
+}
@@ -0,0 +1,173 @@
+
+use super::{io, Read, Write, Operation, App, Arg, ArgMatches, SubCommand, PathBuf, Path, Regex, Entry,
+            Archive, File, Decomp};
+use super::valid::valid_input;
+
+use std::collections::HashMap;
+use std::path::Component;
+
+/// Build the `cat` subcommand
+pub fn build<'a>() -> App<'static, 'a> {
+    SubCommand::with_name("cat")
+        .about("Write the contents of an entry to stdout")
+        .arg(
+            Arg::with_name("file")
+                .index(1)
+                .takes_value(true)
+                .multiple(false)
+                .value_name("INFILE")
+                .required(true)
+                .validator(valid_input)
+                .next_line_help(true)
+                .help("file to read, `-` reads stdin"),
+        )
+        .arg(
+            Arg::with_name("entry")
+                .index(2)
+                .takes_value(true)
+                .multiple(false)
+                .value_name("PATH")
+                .required(true)
+                .next_line_help(true)
+                .help("Path of the entry inside the archive, a file name regex with --all"),
+        )
+        .arg(
+            Arg::with_name("all")
+                .long("all")
+                .takes_value(false)
+                .next_line_help(true)
+                .help("Write every entry whose file name matches PATH as a regex"),
+        )
+}
+
+pub fn get(x: &ArgMatches) -> Operation {
+    let entry = x.value_of("entry").unwrap();
+    Operation::Cat(
+        PathBuf::from(x.value_of("file").unwrap()),
+        PathBuf::from(entry),
+        if x.is_present("all") {
+            match Regex::new(entry) {
+                Ok(r) => Some(r),
+                Err(e) => {
+                    eprintln!("Regex Syntax Error: {}", e);
+                    ::std::process::exit(1);
+                }
+            }
+        } else {
+            None
+        },
+    )
+}
+
+/// The entries read so far which hold data, by name
+///
+/// A hardlink's data is stored under the name it links to, which came
+/// earlier in the archive, so that entry is read again to follow it.
+pub struct Seen {
+    count: usize,
+    names: HashMap<PathBuf, usize>,
+}
+impl Seen {
+    pub fn new() -> Seen {
+        Seen {
+            count: 0,
+            names: HashMap::new(),
+        }
+    }
+}
+
+/// Stream the entry to stdout if it is the one asked for
+///
+/// Hardlinks are followed by reading `archive` again up to the entry they
+/// link to, which isn't possible for stdin. Returns `true` when the entry
+/// matched.
+pub fn exec<R: Read>(
+    entry: &mut Entry<R>,
+    archive: &Path,
+    target: &Path,
+    all: &Option<Regex>,
+    seen: &mut Seen,
+) -> io::Result<bool> {
+    let index = seen.count;
+    seen.count += 1;
+    let kind = entry.header().entry_type();
+    let path = entry.path()?.into_owned();
+    // sparse entries come out of the reader with their holes filled in
+    let data = if kind.is_file() || kind.is_contiguous() || kind.is_gnu_sparse() {
+        Option::Some(index)
+    } else if kind.is_hard_link() {
+        let link = entry.link_name()?.map(|x| key(&x));
+        link.and_then(|x| seen.names.get(&x).cloned())
+    } else {
+        return Ok(false);
+    };
+    if let Option::Some(i) = data {
+        seen.names.insert(key(&path), i);
+    }
+    let flag = match all {
+        &Option::None => same_path(&path, target),
+        &Option::Some(ref regex) => {
+            match path.file_name() {
+                Option::None => false,
+                Option::Some(f_name) => {
+                    match f_name.to_str() {
+                        Option::None => false,
+                        Option::Some(f_name_str) => regex.is_match(f_name_str),
+                    }
+                }
+            }
+        }
+    };
+    if !flag {
+        return Ok(false);
+    }
+    let stdout = io::stdout();
+    let mut lock = stdout.lock();
+    match data {
+        Option::Some(i) if i == index => {
+            io::copy(entry, &mut lock)?;
+        }
+        Option::Some(i) => {
+            reread(archive, i, &mut lock)?;
+        }
+        Option::None => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{:?} links to an entry which isn't in the archive", path),
+            ))
+        }
+    };
+    Ok(true)
+}
+
+/// Open `archive` again and copy the data of its `index`th entry to `w`
+fn reread<W: Write>(archive: &Path, index: usize, w: &mut W) -> io::Result<u64> {
+    if archive == Path::new("-") {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "hardlinks can't be followed when reading stdin",
+        ));
+    }
+    let mut x = Archive::new(Decomp::from_unknown(File::open(archive)?)?);
+    match x.entries()?.nth(index) {
+        Option::Some(e) => io::copy(&mut e?, w),
+        Option::None => Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "archive changed while it was read",
+        )),
+    }
+}
+
+/// A name without its `.` components, to look up links by
+fn key(p: &Path) -> PathBuf {
+    p.components().filter(|x| *x != Component::CurDir).collect()
+}
+
+/// Compare ignoring `.` components, so `./a/b` finds `a/b`
+fn same_path(a: &Path, b: &Path) -> bool {
+    let strip = |x: &Component| match x {
+        &Component::CurDir => false,
+        _ => true,
+    };
+    a.components().filter(&strip).eq(b.components().filter(&strip))
+}
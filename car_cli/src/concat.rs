@@ -0,0 +1,144 @@
+
+use super::{io, Write, Operation, App, Arg, ArgMatches, SubCommand, PathBuf, File, Comp, Decomp,
+            BufWriter};
+use super::valid::valid_path;
+use super::tarball::Members;
+use super::create::{get_format, open_comp, item_exists, FORMATS};
+
+use std::collections::{HashMap, HashSet};
+
+/// Build the `concat` subcommand
+pub fn build<'a>() -> App<'static, 'a> {
+    SubCommand::with_name("concat")
+        .about("Merge several archives into one")
+        .arg(
+            Arg::with_name("file")
+                .short("f")
+                .long("file")
+                .takes_value(true)
+                .multiple(true)
+                .value_name("ARCHIVE")
+                .required(true)
+                .validator(valid_path)
+                .next_line_help(true)
+                .help("archives to merge, in order"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("out")
+                .takes_value(true)
+                .multiple(false)
+                .value_name("OUTFILE")
+                .required(true)
+                .validator(item_exists)
+                .help("tarball output, `-` writes stdout"),
+        )
+        .arg(
+            Arg::with_name("to")
+                .long("to")
+                .takes_value(true)
+                .multiple(false)
+                .value_name("FORMAT")
+                .possible_values(FORMATS)
+                .default_value("tar")
+                .help("compression of the output"),
+        )
+        .arg(
+            Arg::with_name("dedupe")
+                .long("dedupe")
+                .takes_value(false)
+                .next_line_help(true)
+                .help("When a path appears more then once only keep the last copy"),
+        )
+        .arg(
+            Arg::with_name("slow")
+                .long("slow")
+                .takes_value(false)
+                .conflicts_with("fast")
+                .help("sets the slow compression mode"),
+        )
+        .arg(
+            Arg::with_name("fast")
+                .long("fast")
+                .takes_value(false)
+                .conflicts_with("slow")
+                .help("sets the fast compression mode"),
+        )
+}
+
+pub fn get(x: &ArgMatches) -> Operation {
+    Operation::Concat(
+        open_comp(x.value_of("output").unwrap(), get_format(x)),
+        x.values_of("file").unwrap().map(PathBuf::from).collect(),
+        x.is_present("dedupe"),
+    )
+}
+
+/// Write every member of every input into `comp`
+pub fn exec<W: Write>(
+    comp: Comp<W>,
+    inputs: &[PathBuf],
+    dedupe: bool,
+) -> Result<BufWriter<W>, String> {
+    let x = match merge(comp, inputs, dedupe) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("FATAL IO ERROR OCCURED");
+            eprintln!("{:?}", e);
+            ::std::process::exit(1);
+        }
+    };
+    x.finish()
+}
+
+fn merge<W: Write>(comp: Comp<W>, inputs: &[PathBuf], dedupe: bool) -> io::Result<Comp<W>> {
+    let mut comp = comp;
+    // path -> (input, member) of the copy which survives
+    let mut last = HashMap::new();
+    // hardlink -> the copy of its target it was written after
+    let mut links = HashMap::new();
+    if dedupe {
+        for (i, p) in inputs.iter().enumerate() {
+            let mut members = Members::new(Decomp::from_unknown(File::open(p)?)?);
+            let mut j = 0usize;
+            while let Option::Some(m) = members.next_member()? {
+                if m.header.entry_type().is_hard_link() {
+                    let target = m.link.as_ref().and_then(|x| last.get(x)).cloned();
+                    if let Option::Some(target) = target {
+                        links.insert((i, j), target);
+                    }
+                }
+                last.insert(m.path, (i, j));
+                j += 1;
+            }
+        }
+    }
+    // a link is extracted against the copy before it, so that copy stays
+    // even when a later one replaces it
+    let mut needed = HashSet::new();
+    for pos in last.values() {
+        let mut pos = pos;
+        while let Option::Some(target) = links.get(pos) {
+            if !needed.insert(*target) {
+                break;
+            }
+            pos = target;
+        }
+    }
+    for (i, p) in inputs.iter().enumerate() {
+        let mut members = Members::new(Decomp::from_unknown(File::open(p)?)?);
+        let mut j = 0usize;
+        while let Option::Some(m) = members.next_member()? {
+            let keep = !dedupe || last.get(&m.path) == Some(&(i, j)) || needed.contains(&(i, j));
+            j += 1;
+            if keep {
+                comp.write_all(&m.raw)?;
+                members.copy_data(&mut comp)?;
+            }
+        }
+    }
+    // end-of-archive marker
+    comp.write_all(&[0u8; 1024])?;
+    Ok(comp)
+}
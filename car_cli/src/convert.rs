@@ -0,0 +1,143 @@
+
+use super::{io, Write, Operation, App, Arg, ArgMatches, SubCommand, Path, PathBuf, File,
+            Archive, Builder, Header, EntryType, Comp, Decomp, BufWriter};
+use super::valid::valid_path;
+use super::tarball;
+use super::create::{get_format, open_comp, item_exists, FORMATS};
+
+/// Build the `convert` subcommand
+pub fn build<'a>() -> App<'static, 'a> {
+    SubCommand::with_name("convert")
+        .about("Recompress an archive into another format")
+        .arg(
+            Arg::with_name("file")
+                .index(1)
+                .takes_value(true)
+                .multiple(false)
+                .value_name("INFILE")
+                .required(true)
+                .validator(valid_path)
+                .next_line_help(true)
+                .help("archive to read"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .index(2)
+                .takes_value(true)
+                .multiple(false)
+                .value_name("OUTFILE")
+                .required(true)
+                .validator(item_exists)
+                .next_line_help(true)
+                .help("archive to write, `-` writes stdout"),
+        )
+        .arg(
+            Arg::with_name("to")
+                .long("to")
+                .takes_value(true)
+                .multiple(false)
+                .value_name("FORMAT")
+                .possible_values(FORMATS)
+                .required(true)
+                .help("compression of the output"),
+        )
+        .arg(
+            Arg::with_name("repack")
+                .long("repack")
+                .takes_value(false)
+                .next_line_help(true)
+                .help(
+                    "Rebuild every tar header instead of copying the inner tar stream \
+                     byte for byte",
+                ),
+        )
+        .arg(
+            Arg::with_name("slow")
+                .long("slow")
+                .takes_value(false)
+                .conflicts_with("fast")
+                .help("sets the slow compression mode"),
+        )
+        .arg(
+            Arg::with_name("fast")
+                .long("fast")
+                .takes_value(false)
+                .conflicts_with("slow")
+                .help("sets the fast compression mode"),
+        )
+}
+
+pub fn get(x: &ArgMatches) -> Operation {
+    Operation::Convert(
+        PathBuf::from(x.value_of("file").unwrap()),
+        open_comp(x.value_of("output").unwrap(), get_format(x)),
+        x.is_present("repack"),
+    )
+}
+
+/// Decompress `input` straight into `comp`
+pub fn exec<W: Write>(input: &Path, comp: Comp<W>, repack: bool) -> Result<BufWriter<W>, String> {
+    let x = match convert(input, comp, repack) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("FATAL IO ERROR OCCURED");
+            eprintln!("{:?}", e);
+            ::std::process::exit(1);
+        }
+    };
+    x.finish()
+}
+
+fn convert<W: Write>(input: &Path, comp: Comp<W>, repack: bool) -> io::Result<Comp<W>> {
+    let mut comp = comp;
+    let mut decomp = Decomp::from_unknown(File::open(input)?)?;
+    if !repack {
+        io::copy(&mut decomp, &mut comp)?;
+        return Ok(comp);
+    }
+    let mut archive = Archive::new(decomp);
+    let mut builder = Builder::new(comp);
+    for e in archive.entries()? {
+        let mut e = e?;
+        let kind = e.header().entry_type();
+        if kind.is_pax_global_extensions() {
+            continue;
+        }
+        let path = e.path()?.into_owned();
+        let link = e.link_name()?.map(|x| x.into_owned());
+        let mut header = normalize(e.header())?;
+        // sparse members are read back with their holes filled in
+        header.set_size(e.header().size()?);
+        let link = link.as_ref().map(|x| x.as_path());
+        tarball::append_named(&mut builder, &mut header, &path, link, &mut e)?;
+    }
+    builder.into_inner()
+}
+
+/// A fresh GNU header carrying over only the fields `car` itself writes
+fn normalize(old: &Header) -> io::Result<Header> {
+    let mut header = Header::new_gnu();
+    let kind = old.entry_type();
+    header.set_entry_type(if kind.is_gnu_sparse() || kind.is_contiguous() {
+        EntryType::file()
+    } else {
+        kind
+    });
+    header.set_mode(old.mode()?);
+    header.set_uid(old.uid()?);
+    header.set_gid(old.gid()?);
+    header.set_mtime(old.mtime()?);
+    if let Ok(Option::Some(x)) = old.username() {
+        header.set_username(x)?;
+    }
+    if let Ok(Option::Some(x)) = old.groupname() {
+        header.set_groupname(x)?;
+    }
+    if let Option::Some(x) = old.device_major()? {
+        header.set_device_major(x)?;
+    }
+    if let Option::Some(x) = old.device_minor()? {
+        header.set_device_minor(x)?;
+    }
+    Ok(header)
+}
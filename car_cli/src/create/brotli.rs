@@ -1,5 +1,5 @@
-use super::{Format, Quality, App, SubCommand, ArgMatches, Arg, Operation, Comp, File, PathBuf,
-            get_comp_level, valid_item, item_exists};
+use super::{Format, Quality, App, SubCommand, ArgMatches, Arg, Operation, Comp, PathBuf,
+            get_comp_level, item_exists, open_output, get_options, get_items};
 
 pub fn build<'a>() -> App<'static, 'a> {
     SubCommand::with_name("brotli")
@@ -11,8 +11,7 @@ pub fn build<'a>() -> App<'static, 'a> {
                 .takes_value(true)
                 .multiple(true)
                 .value_name("FILE/DIR")
-                .required(true)
-                .validator(valid_item)
+                .required_unless("files-from")
                 .next_line_help(true)
                 .global(true)
                 .help("what to tar"),
@@ -27,7 +26,7 @@ pub fn build<'a>() -> App<'static, 'a> {
                 .required(true)
                 .validator(item_exists)
                 .global(true)
-                .help("tarball output"),
+                .help("tarball output, `-` writes stdout"),
         )
         .arg(
             Arg::with_name("slow")
@@ -48,26 +47,29 @@ pub fn build<'a>() -> App<'static, 'a> {
 }
 
 pub fn get(x: &ArgMatches) -> Operation {
+    let items = get_items(x);
+    let opts = get_options(x);
     Operation::Create(
         {
             let path = x.value_of("output").unwrap();
-            let w = match File::create(&path) {
+            let w = match open_output(&path) {
                 Ok(x) => x,
                 Err(e) => {
-                    println!("Could not create output {}", &path);
-                    println!("Error {:?}", e);
+                    eprintln!("Could not create output {}", &path);
+                    eprintln!("Error {:?}", e);
                     ::std::process::exit(1)
                 }
             };
             match Comp::from_format(Format::Brotli(get_comp_level(x)), w) {
                 Ok(x) => x,
                 Err(e) => {
-                    println!("Building brotli compressor failed");
-                    println!("{:?}", e);
+                    eprintln!("Building brotli compressor failed");
+                    eprintln!("{:?}", e);
                     ::std::process::exit(1);
                 }
             }
         },
-        x.values_of("file").unwrap().map(PathBuf::from).collect(),
+        items,
+        opts,
     )
 }
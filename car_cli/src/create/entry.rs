@@ -0,0 +1,120 @@
+
+use super::{io, Read, Write, Path, Builder, Header, EntryType, File, Options};
+use super::tarball;
+use super::links::Links;
+use super::sparse;
+
+use std::fs::{self, Metadata};
+
+/// Add one filesystem object to the archive under `name`
+///
+/// Directories, symlinks, and (with `--special`) FIFOs and device nodes are
+/// stored as what they are. Sockets, and special files without `--special`,
+/// are skipped. Files `links` has seen already are stored as hardlinks.
+pub fn append<W: Write>(
+    builder: &mut Builder<W>,
+    path: &Path,
+    name: &Path,
+    meta: &Metadata,
+    opts: &Options,
+    links: &mut Links,
+) -> io::Result<()> {
+    let ft = meta.file_type();
+    let mut header = Header::new_gnu();
+    header.set_metadata(meta);
+    // only the permission bits belong in the header, as GNU tar does
+    let mode = header.mode()? & 0o7777;
+    header.set_mode(mode);
+    if let Option::Some(limit) = opts.reproducible {
+        normalize(&mut header, meta, limit)?;
+    }
+    if ft.is_file() {
+        if let Option::Some(target) = links.target(path, name, meta)? {
+            header.set_entry_type(EntryType::hard_link());
+            header.set_size(0);
+            return tarball::append_named(builder, &mut header, name, Some(&target), io::empty());
+        }
+        let f = File::open(path)?;
+        // where the holes are depends on the filesystem, not the contents
+        let map = match opts.reproducible {
+            Option::Some(_) => None,
+            Option::None => sparse::data_map(&f, meta)?,
+        };
+        if let Option::Some(map) = map {
+            let ext = sparse::set_map(&mut header, &map, meta.len());
+            let data = io::Cursor::new(ext).chain(sparse::Data::new(f, map));
+            return tarball::append_named(builder, &mut header, name, None, data);
+        }
+        // a file which grows while it's read must not overrun its header
+        return tarball::append_named(builder, &mut header, name, None, f.take(meta.len()));
+    }
+    header.set_size(0);
+    if ft.is_dir() {
+        return tarball::append_named(builder, &mut header, name, None, io::empty());
+    }
+    if ft.is_symlink() {
+        let target = fs::read_link(path)?;
+        return tarball::append_named(builder, &mut header, name, Some(&target), io::empty());
+    }
+    if opts.special && is_node(meta) {
+        set_device(&mut header, meta)?;
+        return tarball::append_named(builder, &mut header, name, None, io::empty());
+    }
+    Ok(())
+}
+
+/// Drop everything about an entry which depends on the machine it was
+/// archived on, rather than on the file itself
+fn normalize(header: &mut Header, meta: &Metadata, limit: u64) -> io::Result<()> {
+    let mtime = header.mtime()?;
+    header.set_mtime(::std::cmp::min(mtime, limit));
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_username("")?;
+    header.set_groupname("")?;
+    // the only permission bit git keeps is whether a file is executable
+    let mode = header.mode()?;
+    header.set_mode(match meta.file_type() {
+        ft if ft.is_symlink() => 0o777,
+        ft if ft.is_dir() || mode & 0o111 != 0 => 0o755,
+        _ => 0o644,
+    });
+    Ok(())
+}
+
+/// FIFOs and device nodes, which are only archived with `--special`
+#[cfg(unix)]
+pub fn is_node(meta: &Metadata) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    let ft = meta.file_type();
+    ft.is_fifo() || ft.is_char_device() || ft.is_block_device()
+}
+
+#[cfg(windows)]
+pub fn is_node(_: &Metadata) -> bool {
+    false
+}
+
+/// glibc's split of a device number into major and minor
+#[cfg(target_os = "linux")]
+fn set_device(header: &mut Header, meta: &Metadata) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    let dev = meta.rdev();
+    let major = ((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff);
+    let minor = (dev & 0xff) | ((dev >> 12) & !0xff);
+    header.set_device_major(major as u32)?;
+    header.set_device_minor(minor as u32)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn set_device(header: &mut Header, meta: &Metadata) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    let dev = meta.rdev();
+    header.set_device_major(((dev >> 24) & 0xff) as u32)?;
+    header.set_device_minor((dev & 0xffffff) as u32)
+}
+
+#[cfg(windows)]
+fn set_device(_: &mut Header, _: &Metadata) -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,98 @@
+
+use super::{io, Read, Path, PathBuf, File};
+
+extern crate glob;
+use self::glob::Pattern;
+
+/// Names used by version control systems for their own bookkeeping
+const VCS: &'static [&'static str] = &[
+    ".git",
+    ".gitignore",
+    ".gitmodules",
+    ".gitattributes",
+    ".svn",
+    ".hg",
+    ".hgignore",
+    ".hgtags",
+    ".bzr",
+    ".bzrignore",
+    "CVS",
+    ".cvsignore",
+    "RCS",
+    "SCCS",
+    "_darcs",
+    ".arch-ids",
+    "{arch}",
+];
+
+/// Decides which paths are left out of the archive
+///
+/// A pattern without a `/` is compared against every file name, so `*.o`
+/// or `target` match at any depth. A pattern with a `/` is compared against
+/// the whole path as it was walked, e.g. `src/generated/*`.
+pub struct Exclude {
+    names: Vec<Pattern>,
+    paths: Vec<Pattern>,
+    vcs: bool,
+}
+impl Exclude {
+    pub fn new(patterns: &[String], vcs: bool) -> Result<Exclude, String> {
+        let mut names = Vec::new();
+        let mut paths = Vec::new();
+        for p in patterns.iter() {
+            let trimmed = p.trim_right_matches('/');
+            let pattern = match Pattern::new(trimmed) {
+                Ok(x) => x,
+                Err(e) => return Err(format!("Bad exclude pattern {}: {}", p, e)),
+            };
+            if trimmed.contains('/') {
+                paths.push(pattern);
+            } else {
+                names.push(pattern);
+            }
+        }
+        Ok(Exclude {
+            names: names,
+            paths: paths,
+            vcs: vcs,
+        })
+    }
+
+    /// Is `path` left out. Directories which are, are not walked into.
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        if let Option::Some(name) = path.file_name() {
+            let name = name.to_string_lossy();
+            if self.vcs && VCS.contains(&&*name) {
+                return true;
+            }
+            if self.names.iter().any(|p| p.matches(&name)) {
+                return true;
+            }
+        }
+        if self.paths.is_empty() {
+            return false;
+        }
+        let path: PathBuf = path.components()
+            .filter(|c| c.as_os_str() != ".")
+            .collect();
+        self.paths.iter().any(|p| p.matches_path(&path))
+    }
+}
+
+/// Check a single `--exclude` pattern
+pub fn valid_pattern(x: String) -> Result<(), String> {
+    Exclude::new(&[x], false).map(|_| ())
+}
+
+/// Read an `--exclude-from` file, one pattern per line
+pub fn read_patterns(path: &Path) -> io::Result<Vec<String>> {
+    let mut s = String::new();
+    File::open(path)?.read_to_string(&mut s)?;
+    Ok(
+        s.lines()
+            .map(|x| x.trim_right_matches('\r'))
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect(),
+    )
+}
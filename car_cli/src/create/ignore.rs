@@ -0,0 +1,335 @@
+
+use super::{io, Read, Path, PathBuf, File, Options};
+
+use std::env;
+use std::fs::{self, Metadata};
+
+extern crate glob;
+use self::glob::{Pattern, MatchOptions};
+
+/// Files which hold ignore rules for the directory they're in. `.ignore`
+/// is read after `.gitignore` so it wins, as it does for ripgrep.
+const IGNORE_FILES: &'static [&'static str] = &[".gitignore", ".ignore"];
+
+/// One line of an ignore file
+struct Rule {
+    pattern: Pattern,
+    negate: bool,
+    dir_only: bool,
+    /// Matched against the path from the ignore file's directory, rather
+    /// than against the file name
+    anchored: bool,
+}
+
+/// The rules from one ignore file, and the directory they apply under
+struct Rules {
+    base: PathBuf,
+    rules: Vec<Rule>,
+}
+impl Rules {
+    /// A missing file has no rules
+    fn read(base: &Path, file: &Path) -> Option<Rules> {
+        let mut s = String::new();
+        match File::open(file).and_then(|mut f| f.read_to_string(&mut s)) {
+            Ok(_) => Some(Rules {
+                base: base.to_path_buf(),
+                rules: s.lines().filter_map(parse_line).collect(),
+            }),
+            Err(_) => None,
+        }
+    }
+
+    /// `Some(true)` if ignored, `Some(false)` if a `!` rule re-includes
+    /// the path, `None` if no rule mentions it. The last matching rule wins.
+    fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let rel = match path.strip_prefix(&self.base) {
+            Ok(x) => x,
+            Err(_) => return None,
+        };
+        let name = match rel.file_name() {
+            Option::Some(x) => x.to_string_lossy(),
+            Option::None => return None,
+        };
+        let opts = MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+        for r in self.rules.iter().rev() {
+            if r.dir_only && !is_dir {
+                continue;
+            }
+            let hit = match r.anchored {
+                true => r.pattern.matches_path_with(rel, &opts),
+                false => r.pattern.matches_with(&name, &opts),
+            };
+            if hit {
+                return Some(!r.negate);
+            }
+        }
+        None
+    }
+}
+
+/// Translate one line of gitignore syntax
+fn parse_line(line: &str) -> Option<Rule> {
+    let line = line.trim_right_matches('\r');
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (negate, line) = match line.starts_with('!') {
+        true => (true, &line[1..]),
+        false => (false, line),
+    };
+    // trailing spaces are dropped unless escaped
+    let mut line = line.trim_right_matches(' ').to_string();
+    if line.ends_with('\\') {
+        line.push(' ');
+    }
+    let dir_only = line.ends_with('/');
+    let trimmed = line.trim_right_matches('/');
+    let anchored = trimmed.contains('/');
+    let trimmed = trimmed.trim_left_matches('/');
+    if trimmed.is_empty() {
+        return None;
+    }
+    // `\*` is a literal `*`, which glob spells `[*]`
+    let mut glob = String::with_capacity(trimmed.len());
+    let mut chars = trimmed.chars();
+    while let Option::Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                match chars.next() {
+                    Option::Some(e) if "*?[]".contains(e) => glob.push_str(&format!("[{}]", e)),
+                    Option::Some(e) => glob.push(e),
+                    Option::None => {}
+                };
+            }
+            _ => glob.push(c),
+        };
+    }
+    match Pattern::new(&glob) {
+        Ok(p) => Some(Rule {
+            pattern: p,
+            negate: negate,
+            dir_only: dir_only,
+            anchored: anchored,
+        }),
+        Err(_) => None,
+    }
+}
+
+/// Rules in force at some point of the walk, outermost first
+struct Stack {
+    rules: Vec<Rules>,
+}
+impl Stack {
+    fn ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for r in self.rules.iter().rev() {
+            if let Option::Some(x) = r.matched(path, is_dir) {
+                return x;
+            }
+        }
+        false
+    }
+
+    /// Push the rules in `file`, which apply under `base`
+    fn push(&mut self, base: &Path, file: &Path) -> bool {
+        match Rules::read(base, file) {
+            Option::Some(r) => {
+                self.rules.push(r);
+                true
+            }
+            Option::None => false,
+        }
+    }
+
+    /// Push the ignore files found in `dir`, returns how many there were
+    fn enter(&mut self, dir: &Path) -> usize {
+        IGNORE_FILES
+            .iter()
+            .filter(|name| self.push(dir, &dir.join(name)))
+            .count()
+    }
+
+    fn leave(&mut self, n: usize) {
+        let len = self.rules.len();
+        self.rules.truncate(len - n);
+    }
+}
+
+/// Walk `item` leaving out everything git would ignore
+///
+/// Rules come from the global excludes file, the repository's
+/// `.git/info/exclude`, and every `.gitignore` and `.ignore` from the
+/// repository's root down to the file. `.git` itself is never archived.
+///
+/// `f` is called on everything else, it returns `false` for paths which
+/// are excluded, and directories which are aren't walked into.
+pub fn walk<F>(item: &Path, opts: &Options, f: &mut F) -> io::Result<()>
+where
+    F: FnMut(&Path, &Metadata) -> io::Result<bool>,
+{
+    let meta = match opts.dereference {
+        true => fs::metadata(item)?,
+        false => fs::symlink_metadata(item)?,
+    };
+    // rules are matched against absolute paths, since the ones above
+    // `item` are relative to directories which aren't walked
+    let abs = absolute(item)?;
+    let root = match meta.is_dir() {
+        true => repository(&abs),
+        false => abs.parent().and_then(repository),
+    };
+    let mut stack = Stack { rules: Vec::new() };
+    if let Option::Some(path) = global_excludes() {
+        let top = root.as_ref().map_or(abs.as_path(), |x| x.as_path());
+        stack.push(top, &path);
+    }
+    if let Option::Some(root) = root {
+        stack.push(&root, &root.join(".git").join("info").join("exclude"));
+        // every directory from the root down to, but not including, `item`
+        let rest: Vec<_> = abs.strip_prefix(&root).unwrap().components().collect();
+        let mut dir = root.clone();
+        for c in rest.iter() {
+            stack.enter(&dir);
+            dir.push(c);
+        }
+    }
+    visit(item, abs, meta, &mut stack, opts, f)
+}
+
+/// Work left in the walk, kept on a stack rather than in recursion so deep
+/// trees can't overflow
+enum Step {
+    /// A path to pass to `f`, and walk into if it's a directory
+    Visit(PathBuf, PathBuf, Metadata),
+    /// Every child of a directory is done, drop its rules
+    Leave(usize),
+}
+
+fn visit<F>(
+    item: &Path,
+    abs: PathBuf,
+    meta: Metadata,
+    stack: &mut Stack,
+    opts: &Options,
+    f: &mut F,
+) -> io::Result<()>
+where
+    F: FnMut(&Path, &Metadata) -> io::Result<bool>,
+{
+    let mut todo = vec![Step::Visit(item.to_path_buf(), abs, meta)];
+    // the directories being walked, to spot symlinks leading back to them
+    let mut ancestors: Vec<PathBuf> = Vec::new();
+    while let Option::Some(step) = todo.pop() {
+        let (path, abs, meta) = match step {
+            Step::Visit(path, abs, meta) => (path, abs, meta),
+            Step::Leave(n) => {
+                stack.leave(n);
+                ancestors.pop();
+                continue;
+            }
+        };
+        let is_dir = meta.is_dir();
+        if path.file_name().map_or(false, |n| n == ".git") || stack.ignored(&abs, is_dir) {
+            continue;
+        }
+        if !f(&path, &meta)? || !is_dir || !opts.recursion {
+            continue;
+        }
+        let real = match fs::canonicalize(&path) {
+            Ok(x) => x,
+            Err(_) => continue,
+        };
+        if ancestors.contains(&real) {
+            continue;
+        }
+        let mut names: Vec<_> = match fs::read_dir(&path) {
+            Ok(rd) => rd.filter_map(|x| x.ok()).map(|x| x.file_name()).collect(),
+            Err(_) => continue,
+        };
+        names.sort();
+        ancestors.push(real);
+        let n = stack.enter(&abs);
+        todo.push(Step::Leave(n));
+        // pushed last to first so they come off the stack in order
+        for name in names.iter().rev() {
+            let child = path.join(name);
+            let meta = match opts.dereference {
+                true => fs::metadata(&child),
+                false => fs::symlink_metadata(&child),
+            };
+            if let Ok(meta) = meta {
+                todo.push(Step::Visit(child, abs.join(name), meta));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `p` from the root, without resolving `p` itself in case it's a symlink
+fn absolute(p: &Path) -> io::Result<PathBuf> {
+    let p = env::current_dir()?.join(p);
+    match (p.parent(), p.file_name()) {
+        (Option::Some(dir), Option::Some(name)) => Ok(fs::canonicalize(dir)?.join(name)),
+        _ => fs::canonicalize(p),
+    }
+}
+
+/// The working tree holding `path`, if there is one
+fn repository(dir: &Path) -> Option<PathBuf> {
+    let mut dir = Some(dir);
+    while let Option::Some(d) = dir {
+        if d.join(".git").exists() {
+            return Some(d.to_path_buf());
+        }
+        dir = d.parent();
+    }
+    None
+}
+
+/// `core.excludesFile`, or git's default of `$XDG_CONFIG_HOME/git/ignore`
+fn global_excludes() -> Option<PathBuf> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    let configured = home.as_ref().and_then(|h| {
+        let mut s = String::new();
+        match File::open(h.join(".gitconfig")).and_then(|mut f| f.read_to_string(&mut s)) {
+            Ok(_) => excludes_file(&s, h),
+            Err(_) => None,
+        }
+    });
+    match configured {
+        Option::Some(p) => Some(p),
+        Option::None => {
+            match env::var_os("XDG_CONFIG_HOME") {
+                Option::Some(x) => Some(PathBuf::from(x).join("git").join("ignore")),
+                Option::None => home.map(|h| h.join(".config").join("git").join("ignore")),
+            }
+        }
+    }
+}
+
+/// Find `excludesfile` in the `[core]` section of a git config
+fn excludes_file(config: &str, home: &Path) -> Option<PathBuf> {
+    let mut core = false;
+    for line in config.lines().map(|x| x.trim()) {
+        if line.starts_with('[') {
+            core = line.to_lowercase().starts_with("[core");
+            continue;
+        }
+        if !core {
+            continue;
+        }
+        let mut kv = line.splitn(2, '=');
+        let key = kv.next().unwrap_or("").trim().to_lowercase();
+        if key == "excludesfile" {
+            let value = kv.next().unwrap_or("").trim().trim_matches('"');
+            return Some(match value.starts_with("~/") {
+                true => home.join(&value[2..]),
+                false => PathBuf::from(value),
+            });
+        }
+    }
+    None
+}
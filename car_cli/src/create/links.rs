@@ -0,0 +1,112 @@
+
+use super::{io, Read, Path, PathBuf, File};
+use super::tarball::fill;
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::Metadata;
+use std::hash::Hasher;
+
+/// Remembers what has been archived, so files seen again become hardlinks
+///
+/// Every path to an inode after the first is stored as a link to the first
+/// member name. With `--dedupe` files with identical contents are linked
+/// too, they then share the metadata of the first copy once extracted.
+///
+/// `--reproducible` archives don't depend on which paths share an inode,
+/// only `--dedupe` links files there.
+pub struct Links {
+    /// `None` when inodes are ignored
+    inodes: Option<HashMap<(u64, u64), PathBuf>>,
+    /// Files keyed by size and hash, with the path they were read from
+    contents: Option<HashMap<(u64, u64), Vec<(PathBuf, PathBuf)>>>,
+}
+impl Links {
+    pub fn new(dedupe: bool, reproducible: bool) -> Links {
+        Links {
+            inodes: match reproducible {
+                true => None,
+                false => Some(HashMap::new()),
+            },
+            contents: match dedupe {
+                true => Some(HashMap::new()),
+                false => None,
+            },
+        }
+    }
+
+    /// The member which the regular file at `path` should link to, or
+    /// `None` if it has to be archived in full under `name`
+    pub fn target(&mut self, path: &Path, name: &Path, meta: &Metadata) -> io::Result<Option<PathBuf>> {
+        let inode = self.inodes.as_ref().and_then(|_| inode(meta));
+        if let Option::Some(first) = inode.and_then(|k| self.inodes.as_ref().unwrap().get(&k)) {
+            return Ok(Some(first.clone()));
+        }
+        let found = self.same_contents(path, name, meta)?;
+        if let Option::Some(key) = inode {
+            let first = found.clone().unwrap_or(name.to_path_buf());
+            self.inodes.as_mut().unwrap().insert(key, first);
+        }
+        Ok(found)
+    }
+
+    fn same_contents(&mut self, path: &Path, name: &Path, meta: &Metadata) -> io::Result<Option<PathBuf>> {
+        let contents = match self.contents {
+            Option::Some(ref mut x) => x,
+            Option::None => return Ok(None),
+        };
+        if meta.len() == 0 {
+            return Ok(None);
+        }
+        let seen = contents.entry((meta.len(), hash(path)?)).or_insert_with(Vec::new);
+        // the hash only narrows it down, the bytes decide
+        for &(ref p, ref n) in seen.iter() {
+            if identical(p, path)? {
+                return Ok(Some(n.clone()));
+            }
+        }
+        seen.push((path.to_path_buf(), name.to_path_buf()));
+        Ok(None)
+    }
+}
+
+/// Only files with other names are worth remembering
+#[cfg(unix)]
+fn inode(meta: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    match meta.nlink() > 1 {
+        true => Some((meta.dev(), meta.ino())),
+        false => None,
+    }
+}
+
+#[cfg(windows)]
+fn inode(_: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+fn hash(path: &Path) -> io::Result<u64> {
+    let mut f = File::open(path)?;
+    let mut h = DefaultHasher::new();
+    let mut buf = vec![0u8; 65536];
+    loop {
+        match f.read(&mut buf)? {
+            0 => return Ok(h.finish()),
+            n => h.write(&buf[..n]),
+        };
+    }
+}
+
+fn identical(a: &Path, b: &Path) -> io::Result<bool> {
+    let (mut fa, mut fb) = (File::open(a)?, File::open(b)?);
+    let (mut ba, mut bb) = (vec![0u8; 65536], vec![0u8; 65536]);
+    loop {
+        let n = fill(&mut fa, &mut ba)?;
+        if n != fill(&mut fb, &mut bb)? || ba[..n] != bb[..n] {
+            return Ok(false);
+        }
+        if n == 0 {
+            return Ok(true);
+        }
+    }
+}
@@ -0,0 +1,30 @@
+
+use super::{io, Read, PathBuf, File};
+use super::tarball;
+
+/// Read a `--files-from` list, `-` reads stdin
+///
+/// Entries are one per line, or separated by NULs when `null` is set so
+/// names may hold newlines. Empty entries are skipped.
+pub fn read_list(path: &str, null: bool) -> io::Result<Vec<PathBuf>> {
+    let mut buf = Vec::new();
+    if path == "-" {
+        io::stdin().read_to_end(&mut buf)?;
+    } else {
+        File::open(path)?.read_to_end(&mut buf)?;
+    }
+    let sep = match null {
+        true => 0u8,
+        false => b'\n',
+    };
+    Ok(
+        buf.split(|b| *b == sep)
+            .map(|x| match (null, x.last()) {
+                (false, Option::Some(&b'\r')) => &x[..x.len() - 1],
+                _ => x,
+            })
+            .filter(|x| !x.is_empty())
+            .map(tarball::bytes_to_path)
+            .collect(),
+    )
+}
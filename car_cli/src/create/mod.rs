@@ -1,12 +1,15 @@
 
 #![allow(unused_imports)]
-use super::{Format, Quality, App, SubCommand, ArgMatches, Arg, io, Write, Comp, PathBuf,
-            Operation, Builder, File};
+use super::{Format, Quality, App, SubCommand, ArgMatches, Arg, io, Read, Write, Comp, PathBuf,
+            Operation, Builder, Header, EntryType, File};
+use super::tarball;
 
 use std::io::BufWriter;
+use std::fs::{self, Metadata};
+use std::path::{Path, Component};
 
 extern crate walkdir;
-use self::walkdir::WalkDir;
+use self::walkdir::{WalkDir, WalkDirIterator};
 
 mod tar;
 mod snap;
@@ -16,17 +19,39 @@ mod brotli;
 mod gzip;
 mod bzip2;
 mod xz;
+mod entry;
+mod exclude;
+mod ignore;
+mod list;
+mod links;
+mod sparse;
+pub use self::exclude::Exclude;
+use self::links::Links;
 
-pub fn valid_item(x: String) -> Result<(), String> {
-    let p = PathBuf::from(&x);
-    match (p.exists(), p.is_file() | p.is_dir()) {
-        (true, true) => Ok(()),
-        (false, _) => Err(format!("Cannot process {} it does not exist", &x)),
-        (true, false) => Err(format!("Cannot process {} it is something special", &x)),
+/// Items are checked once `-C` is known, rather than by clap
+///
+/// They're checked the way they'll be archived, a symlink is only
+/// followed with `--dereference` and nodes need `--special`.
+fn check_item(p: &Path, special: bool, dereference: bool) -> Result<(), String> {
+    let meta = match dereference {
+        true => fs::metadata(p),
+        false => fs::symlink_metadata(p),
+    };
+    let meta = match meta {
+        Ok(x) => x,
+        Err(_) => return Err(format!("Cannot process {} it does not exist", p.display())),
+    };
+    let ft = meta.file_type();
+    match ft.is_file() || ft.is_dir() || ft.is_symlink() || (special && entry::is_node(&meta)) {
+        true => Ok(()),
+        false => Err(format!("Cannot process {} it is something special", p.display())),
     }
 }
 
 pub fn item_exists(x: String) -> Result<(), String> {
+    if x == "-" {
+        return Ok(());
+    }
     let p = PathBuf::from(&x);
     if p.exists() {
         Err(format!("{} exists\nCowardly refusing to delete it", &x))
@@ -45,18 +70,363 @@ pub fn get_comp_level(x: &ArgMatches) -> Quality {
     Quality::Default
 }
 
+/// Values accepted by `--to`, these mirror the `create` subcommands
+pub const FORMATS: &'static [&'static str] = &[
+    "tar",
+    "snappy",
+    "lz4",
+    "zstd",
+    "brotli",
+    "gzip",
+    "bzip2",
+    "xz",
+];
+
+/// Read the `--to` argument (and compression level) into a `Format`
+pub fn get_format(x: &ArgMatches) -> Format {
+    let q = get_comp_level(x);
+    match x.value_of("to").unwrap() {
+        "snappy" => Format::Snappy(q),
+        "lz4" => Format::Lz4(q),
+        "zstd" => Format::Zstd(q),
+        "brotli" => Format::Brotli(q),
+        "gzip" => Format::Gzip(q),
+        "bzip2" => Format::Bzip2(q),
+        "xz" => Format::Xz(q),
+        _ => Format::Tar(q),
+    }
+}
+
+/// Create the output file, `-` writes to stdout
+pub fn open_output(path: &str) -> io::Result<Box<Write>> {
+    if path == "-" {
+        Ok(Box::new(io::stdout()))
+    } else {
+        Ok(Box::new(File::create(path)?))
+    }
+}
+
+/// Create the output file and its compressor
+pub fn open_comp(path: &str, f: Format) -> Comp<Box<Write>> {
+    let w = match open_output(&path) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Could not create output {}", &path);
+            eprintln!("Error {:?}", e);
+            ::std::process::exit(1)
+        }
+    };
+    match Comp::from_format(f, w) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("Building compressor failed");
+            eprintln!("{:?}", e);
+            ::std::process::exit(1);
+        }
+    }
+}
+
+/// Flags which change what is archived, and how
+pub struct Options {
+    pub dereference: bool,
+    pub special: bool,
+    pub exclude: Exclude,
+    pub gitignore: bool,
+    /// Cleared by `--no-recursion`, directories are archived without
+    /// their contents
+    pub recursion: bool,
+    /// `-C`, items are found relative to this but named as given
+    pub directory: PathBuf,
+    /// `--prefix`, already normalized
+    pub prefix: PathBuf,
+    pub dedupe: bool,
+    /// Set by `--reproducible`, the latest mtime any entry may have
+    pub reproducible: Option<u64>,
+}
+
+/// Arguments shared by every format, and by `append` and `update`
+pub fn shared(app: App<'static, 'static>) -> App<'static, 'static> {
+    app.arg(
+        Arg::with_name("dereference")
+            .short("L")
+            .long("dereference")
+            .takes_value(false)
+            .next_line_help(true)
+            .help("Follow symlinks, archiving the files they point to"),
+    ).arg(
+        Arg::with_name("special")
+            .long("special")
+            .takes_value(false)
+            .next_line_help(true)
+            .help("Archive FIFOs and device nodes, which are skipped otherwise (Unix only)"),
+    ).arg(
+        Arg::with_name("exclude")
+            .long("exclude")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("GLOB")
+            .validator(exclude::valid_pattern)
+            .next_line_help(true)
+            .help(
+                "Leave out paths matching GLOB, directories are not walked into. \
+                 Without a `/` GLOB is matched against file names",
+            ),
+    ).arg(
+        Arg::with_name("exclude-from")
+            .long("exclude-from")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("FILE")
+            .validator(valid_list)
+            .next_line_help(true)
+            .help("Read exclude patterns from FILE, one per line"),
+    ).arg(
+        Arg::with_name("exclude-vcs")
+            .long("exclude-vcs")
+            .takes_value(false)
+            .next_line_help(true)
+            .help("Leave out version control directories and files, like .git"),
+    ).arg(
+        Arg::with_name("respect-gitignore")
+            .long("respect-gitignore")
+            .takes_value(false)
+            .next_line_help(true)
+            .help(
+                "Leave out what git would ignore, from .gitignore, .ignore, \
+                 .git/info/exclude and the global excludes file",
+            ),
+    ).arg(
+        Arg::with_name("reproducible")
+            .long("reproducible")
+            .takes_value(false)
+            .next_line_help(true)
+            .help(
+                "Give the same bytes for the same files on any machine. Entries are \
+                 sorted, owners and names zeroed, modes set to 644 or 755 and mtimes \
+                 clamped to --mtime, $SOURCE_DATE_EPOCH, or 0",
+            ),
+    ).arg(
+        Arg::with_name("mtime")
+            .long("mtime")
+            .takes_value(true)
+            .value_name("SECONDS")
+            .requires("reproducible")
+            .validator(valid_mtime)
+            .next_line_help(true)
+            .help("Latest mtime in a --reproducible archive, as seconds since 1970"),
+    ).arg(
+        Arg::with_name("files-from")
+            .long("files-from")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .value_name("FILE")
+            .validator(valid_files_from)
+            .next_line_help(true)
+            .help("Archive the paths listed in FILE as well as any -f, `-` reads stdin"),
+    ).arg(
+        Arg::with_name("null")
+            .long("null")
+            .takes_value(false)
+            .requires("files-from")
+            .next_line_help(true)
+            .help("--files-from lists are separated by NULs rather than newlines"),
+    ).arg(
+        Arg::with_name("no-recursion")
+            .long("no-recursion")
+            .takes_value(false)
+            .next_line_help(true)
+            .help("Archive directories without their contents"),
+    ).arg(
+        Arg::with_name("directory")
+            .short("C")
+            .long("directory")
+            .takes_value(true)
+            .value_name("DIR")
+            .validator(valid_directory)
+            .next_line_help(true)
+            .help("Find the items to archive in DIR, they are named relative to it"),
+    ).arg(
+        Arg::with_name("prefix")
+            .long("prefix")
+            .takes_value(true)
+            .value_name("NAME/")
+            .next_line_help(true)
+            .help("Store every member under the directory NAME/"),
+    ).arg(
+        Arg::with_name("dedupe")
+            .long("dedupe")
+            .takes_value(false)
+            .next_line_help(true)
+            .help(
+                "Store files with the same contents once, later copies become hardlinks \
+                 to the first. Files which are already hardlinked always are",
+            ),
+    )
+}
+
+fn valid_directory(x: String) -> Result<(), String> {
+    match Path::new(&x).is_dir() {
+        true => Ok(()),
+        false => Err(format!("{} is not a directory", &x)),
+    }
+}
+
+/// Make `path` relative, resolving `.` and `..` without leaving the top,
+/// so nothing extracts outside of where it's extracted to
+pub fn normalize_name(path: &Path) -> PathBuf {
+    let mut parts = Vec::new();
+    for c in path.components() {
+        match c {
+            Component::Normal(x) => parts.push(x),
+            Component::ParentDir => {
+                parts.pop();
+            }
+            _ => {}
+        };
+    }
+    parts.iter().collect()
+}
+
+fn valid_files_from(x: String) -> Result<(), String> {
+    if x == "-" {
+        return Ok(());
+    }
+    match File::open(&x) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Cannot read {}: {}", &x, e)),
+    }
+}
+
+/// The `-f` items, then everything listed by `--files-from`
+///
+/// Call this before creating the output, so a missing item leaves no
+/// empty archive behind.
+pub fn get_items(x: &ArgMatches) -> Vec<PathBuf> {
+    let mut items: Vec<PathBuf> = match x.values_of("file") {
+        Option::Some(v) => v.map(PathBuf::from).collect(),
+        Option::None => Vec::new(),
+    };
+    if let Option::Some(lists) = x.values_of("files-from") {
+        for l in lists {
+            let listed = match list::read_list(l, x.is_present("null")) {
+                Ok(v) => v,
+                Err(e) => {
+                    eprintln!("Could not read {}", l);
+                    eprintln!("{:?}", e);
+                    ::std::process::exit(1);
+                }
+            };
+            items.extend(listed);
+        }
+    }
+    let dir = Path::new(x.value_of("directory").unwrap_or(""));
+    let (special, dereference) = (x.is_present("special"), x.is_present("dereference"));
+    for p in items.iter() {
+        if let Err(e) = check_item(&dir.join(p), special, dereference) {
+            eprintln!("{}", e);
+            ::std::process::exit(1);
+        }
+    }
+    items
+}
+
+fn valid_mtime(x: String) -> Result<(), String> {
+    match u64::from_str_radix(&x, 10) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("{} is not a number of seconds: {:?}", x, e)),
+    }
+}
+
+/// `--mtime`, else `$SOURCE_DATE_EPOCH`, else the epoch itself
+fn clamp_mtime(x: &ArgMatches) -> u64 {
+    if let Option::Some(v) = x.value_of("mtime") {
+        return u64::from_str_radix(v, 10).unwrap();
+    }
+    match ::std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(v) => {
+            match u64::from_str_radix(v.trim(), 10) {
+                Ok(x) => x,
+                Err(e) => {
+                    eprintln!("SOURCE_DATE_EPOCH={} is not a number of seconds", v);
+                    eprintln!("{:?}", e);
+                    ::std::process::exit(1);
+                }
+            }
+        }
+        Err(_) => 0,
+    }
+}
+
+/// Build the compressor for a new archive, see `--reproducible`
+pub fn new_comp<W: Write>(f: Format, w: W, opts: &Options) -> io::Result<Comp<W>> {
+    match opts.reproducible {
+        Option::Some(_) => Comp::from_format_reproducible(f, w),
+        Option::None => Comp::from_format(f, w),
+    }
+}
+
+fn valid_list(x: String) -> Result<(), String> {
+    match exclude::read_patterns(Path::new(&x)) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Cannot read {}: {}", &x, e)),
+    }
+}
+
+pub fn get_options(x: &ArgMatches) -> Options {
+    let mut patterns: Vec<String> = match x.values_of("exclude") {
+        Option::Some(v) => v.map(String::from).collect(),
+        Option::None => Vec::new(),
+    };
+    if let Option::Some(files) = x.values_of("exclude-from") {
+        for f in files {
+            match exclude::read_patterns(Path::new(f)) {
+                Ok(v) => patterns.extend(v),
+                Err(e) => {
+                    eprintln!("Could not read {}", f);
+                    eprintln!("{:?}", e);
+                    ::std::process::exit(1);
+                }
+            };
+        }
+    }
+    let exclude = match Exclude::new(&patterns, x.is_present("exclude-vcs")) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("{}", e);
+            ::std::process::exit(1);
+        }
+    };
+    Options {
+        dereference: x.is_present("dereference"),
+        special: x.is_present("special"),
+        exclude: exclude,
+        gitignore: x.is_present("respect-gitignore"),
+        recursion: !x.is_present("no-recursion"),
+        directory: PathBuf::from(x.value_of("directory").unwrap_or("")),
+        prefix: normalize_name(Path::new(x.value_of("prefix").unwrap_or(""))),
+        dedupe: x.is_present("dedupe"),
+        reproducible: match x.is_present("reproducible") {
+            true => Some(clamp_mtime(x)),
+            false => None,
+        },
+    }
+}
+
 /// Build command
 pub fn build<'a>() -> App<'static, 'a> {
     SubCommand::with_name("create")
         .about("Create a tar archive")
-        .subcommand(tar::build())
-        .subcommand(snap::build())
-        .subcommand(lz4::build())
-        .subcommand(zstd::build())
-        .subcommand(brotli::build())
-        .subcommand(gzip::build())
-        .subcommand(bzip2::build())
-        .subcommand(xz::build())
+        .subcommand(shared(tar::build()))
+        .subcommand(shared(snap::build()))
+        .subcommand(shared(lz4::build()))
+        .subcommand(shared(zstd::build()))
+        .subcommand(shared(brotli::build()))
+        .subcommand(shared(gzip::build()))
+        .subcommand(shared(bzip2::build()))
+        .subcommand(shared(xz::build()))
 }
 
 /// Get a sub command
@@ -93,25 +463,69 @@ pub fn get(x: &ArgMatches) -> Operation {
         Option::Some(x) => return xz::get(x),
         Option::None => {}
     };
-    println!("I didn't understand that");
-    println!("Try running `--help`");
+    eprintln!("I didn't understand that");
+    eprintln!("Try running `--help`");
     ::std::process::exit(1);
 }
 
-fn building<W: Write>(c: Comp<W>, items: &[PathBuf]) -> io::Result<Comp<W>> {
+pub fn building<W: Write>(c: Comp<W>, items: &[PathBuf], opts: &Options) -> io::Result<Comp<W>> {
+    building_filtered(c, items, opts, |_, _| true)
+}
+
+/// Only members for which `keep(name, metadata)` returns `true` are archived
+pub fn building_filtered<W, F>(
+    c: Comp<W>,
+    items: &[PathBuf],
+    opts: &Options,
+    keep: F,
+) -> io::Result<Comp<W>>
+where
+    W: Write,
+    F: Fn(&Path, &Metadata) -> bool,
+{
     let mut builder = Builder::new(c);
-    for path in items.iter() {
-        if path.is_dir() {
-            for wd in WalkDir::new(path)
-                .into_iter()
-                .filter_map(|x| x.ok())
-                .filter(|x| x.file_type().is_file())
-            {
-                builder.append_path(wd.path())?;
+    let mut links = Links::new(opts.dedupe, opts.reproducible.is_some());
+    for item in items.iter() {
+        let root = opts.directory.join(item);
+        // what was walked, as the user named it
+        let given = |p: &Path| item.join(p.strip_prefix(&root).unwrap());
+        let mut add = |p: &Path, meta: &Metadata| -> io::Result<()> {
+            let name = opts.prefix.join(normalize_name(&given(p)));
+            // the top of `-f .` has no name of its own
+            if name.as_os_str().is_empty() || !keep(&name, meta) {
+                return Ok(());
             }
+            entry::append(&mut builder, p, &name, meta, opts, &mut links)
+        };
+        if opts.gitignore {
+            ignore::walk(&root, opts, &mut |p, meta| {
+                if opts.exclude.is_excluded(&given(p)) {
+                    return Ok(false);
+                }
+                add(p, meta).map(|_| true)
+            })?;
+            continue;
+        }
+        // WalkDir always follows a symlink it's started on
+        let meta = fs::symlink_metadata(&root)?;
+        if meta.file_type().is_symlink() && !opts.dereference {
+            if !opts.exclude.is_excluded(&given(&root)) {
+                add(&root, &meta)?;
+            }
+            continue;
+        }
+        let mut walk = WalkDir::new(&root).follow_links(opts.dereference);
+        if !opts.recursion {
+            walk = walk.max_depth(0);
+        }
+        if opts.reproducible.is_some() {
+            walk = walk.sort_by(|a, b| a.cmp(b));
         }
-        if path.is_file() {
-            builder.append_path(path)?;
+        for wd in walk.into_iter()
+            .filter_entry(|e| !opts.exclude.is_excluded(&given(e.path())))
+            .filter_map(|x| x.ok())
+        {
+            add(wd.path(), &wd.metadata()?)?;
         }
     }
     builder.into_inner()
@@ -119,12 +533,16 @@ fn building<W: Write>(c: Comp<W>, items: &[PathBuf]) -> io::Result<Comp<W>> {
 
 
 /// execute compressiong
-pub fn exec<W: Write>(x: Comp<W>, items: &[PathBuf]) -> Result<BufWriter<W>, String> {
-    let x = match building(x, items) {
+pub fn exec<W: Write>(
+    x: Comp<W>,
+    items: &[PathBuf],
+    opts: &Options,
+) -> Result<BufWriter<W>, String> {
+    let x = match building(x, items, opts) {
         Ok(x) => x,
         Err(e) => {
-            println!("FATAL IO ERROR OCCURED");
-            println!("{:?}", e);
+            eprintln!("FATAL IO ERROR OCCURED");
+            eprintln!("{:?}", e);
             ::std::process::exit(1);
         }
     };
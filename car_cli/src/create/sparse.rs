@@ -0,0 +1,213 @@
+
+use super::{io, Read, File, Header, EntryType};
+
+use std::cmp;
+use std::fs::Metadata;
+use std::io::{Seek, SeekFrom};
+
+#[cfg(target_os = "linux")]
+extern crate libc;
+
+/// The regions of a file which hold data, as `(offset, length)`
+pub type Map = Vec<(u64, u64)>;
+
+/// Where `f` holds data, `None` if it has no holes
+///
+/// Files are only probed when they take up less space than their length.
+/// Every region but the last is a multiple of 512 bytes, which is where the
+/// next one has to start within the archive.
+#[cfg(target_os = "linux")]
+pub fn data_map(f: &File, meta: &Metadata) -> io::Result<Option<Map>> {
+    use std::os::unix::fs::MetadataExt;
+    use std::os::unix::io::AsRawFd;
+    let len = meta.len();
+    if meta.blocks() * 512 >= len {
+        return Ok(None);
+    }
+    let fd = f.as_raw_fd();
+    let mut map: Map = Vec::new();
+    let mut pos = 0;
+    while pos < len {
+        let data = unsafe { libc::lseek(fd, pos as libc::off_t, libc::SEEK_DATA) };
+        if data < 0 {
+            let e = io::Error::last_os_error();
+            return match e.raw_os_error() {
+                // the rest of the file is a hole
+                Option::Some(libc::ENXIO) => break,
+                // the filesystem can't tell us
+                Option::Some(libc::EINVAL) => Ok(None),
+                _ => Err(e),
+            };
+        }
+        let hole = unsafe { libc::lseek(fd, data, libc::SEEK_HOLE) };
+        if hole < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let start = data as u64 / 512 * 512;
+        let end = cmp::min((hole as u64 + 511) / 512 * 512, len);
+        match map.last_mut() {
+            Option::Some(last) if last.0 + last.1 >= start => last.1 = end - last.0,
+            _ => map.push((start, end - start)),
+        };
+        pos = end;
+    }
+    match map.len() == 1 && map[0] == (0, len) {
+        true => Ok(None),
+        false => Ok(Some(map)),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn data_map(_: &File, _: &Metadata) -> io::Result<Option<Map>> {
+    Ok(None)
+}
+
+/// Make `header` a GNU sparse header for `map`
+///
+/// The first four regions fit in the header itself, the rest go in the
+/// extension blocks which are returned. Those are written between the
+/// header and the data.
+pub fn set_map(header: &mut Header, map: &Map, len: u64) -> Vec<u8> {
+    header.set_entry_type(EntryType::new(b'S'));
+    let mut regions = map.clone();
+    // readers find the length of a file which ends in a hole from here
+    if regions.last().map_or(true, |x| x.0 + x.1 != len) {
+        regions.push((len, 0));
+    }
+    let mut ext = Vec::new();
+    let gnu = header.as_gnu_mut().unwrap();
+    number(&mut gnu.size, map.iter().map(|x| x.1).sum());
+    number(&mut gnu.realsize, len);
+    for (slot, r) in gnu.sparse.iter_mut().zip(regions.iter()) {
+        number(&mut slot.offset, r.0);
+        number(&mut slot.numbytes, r.1);
+    }
+    if regions.len() > 4 {
+        gnu.isextended[0] = 1;
+        let blocks: Vec<_> = regions[4..].chunks(21).collect();
+        for (i, chunk) in blocks.iter().enumerate() {
+            let mut block = [0u8; 512];
+            for (j, r) in chunk.iter().enumerate() {
+                number(&mut block[j * 24..j * 24 + 12], r.0);
+                number(&mut block[j * 24 + 12..j * 24 + 24], r.1);
+            }
+            if i + 1 < blocks.len() {
+                block[21 * 24] = 1;
+            }
+            ext.extend_from_slice(&block);
+        }
+    }
+    ext
+}
+
+/// Write `n` into a 12 byte numeric field, as 11 octal digits if it fits
+/// or else base-256 as GNU tar does
+fn number(dst: &mut [u8], n: u64) {
+    if n < 1 << 33 {
+        let s = format!("{:011o}", n);
+        dst[..11].copy_from_slice(s.as_bytes());
+        dst[11] = 0;
+        return;
+    }
+    for b in dst.iter_mut() {
+        *b = 0;
+    }
+    dst[0] = 0x80;
+    for (i, b) in dst.iter_mut().rev().take(8).enumerate() {
+        *b = (n >> (8 * i)) as u8;
+    }
+}
+
+/// Reads the regions of a sparse file back to back
+pub struct Data {
+    file: File,
+    map: Map,
+    next: usize,
+    left: u64,
+}
+impl Data {
+    pub fn new(file: File, map: Map) -> Data {
+        Data {
+            file: file,
+            map: map,
+            next: 0,
+            left: 0,
+        }
+    }
+}
+impl Read for Data {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.left == 0 {
+            if self.next == self.map.len() {
+                return Ok(0);
+            }
+            let (offset, len) = self.map[self.next];
+            self.next += 1;
+            self.file.seek(SeekFrom::Start(offset))?;
+            self.left = len;
+        }
+        let max = cmp::min(buf.len() as u64, self.left) as usize;
+        let n = self.file.read(&mut buf[..max])?;
+        if n == 0 {
+            // the header already promised this much
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "file shrank while it was archived",
+            ));
+        }
+        self.left -= n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::io::{Cursor, Write};
+    use std::path::Path;
+    use tar::{Archive, Builder};
+    use tarball::{self, Members};
+
+    /// Past 8 GiB the length no longer fits in octal and goes in base-256
+    #[test]
+    fn round_trip_over_8_gib() {
+        let len: u64 = (8 << 30) + 4096;
+        let path = env::temp_dir().join(format!("car-sparse-{}", ::std::process::id()));
+        {
+            let mut f = File::create(&path).unwrap();
+            f.write_all(&[b'a'; 512]).unwrap();
+            f.seek(SeekFrom::Start(len - 512)).unwrap();
+            f.write_all(&[b'z'; 512]).unwrap();
+        }
+        let map = vec![(0, 512), (len - 512, 512)];
+        let mut header = Header::new_gnu();
+        header.set_mode(0o644);
+        let ext = set_map(&mut header, &map, len);
+        let data = Cursor::new(ext).chain(Data::new(File::open(&path).unwrap(), map));
+        let mut builder = Builder::new(Vec::new());
+        let wrote = tarball::append_named(&mut builder, &mut header, Path::new("big"), None, data);
+        fs::remove_file(&path).unwrap();
+        wrote.unwrap();
+        let bytes = builder.into_inner().unwrap();
+
+        let mut archive = Archive::new(Cursor::new(&bytes[..]));
+        let entry = archive.entries().unwrap().next().unwrap().unwrap();
+        assert!(entry.header().entry_type().is_gnu_sparse());
+        assert_eq!(entry.header().size().unwrap(), len);
+        assert_eq!(entry.header().entry_size().unwrap(), 1024);
+        let gnu = entry.header().as_gnu().unwrap();
+        assert_eq!(gnu.real_size().unwrap(), len);
+        assert_eq!(gnu.sparse[1].offset().unwrap(), len - 512);
+        assert_eq!(gnu.sparse[1].length().unwrap(), 512);
+        let at = entry.raw_file_position() as usize;
+        assert!(bytes[at..at + 512].iter().all(|b| *b == b'a'));
+        assert!(bytes[at + 512..at + 1024].iter().all(|b| *b == b'z'));
+
+        let mut members = Members::new(Cursor::new(&bytes[..]));
+        let member = members.next_member().unwrap().unwrap();
+        assert_eq!(member.path, Path::new("big"));
+        assert!(members.next_member().unwrap().is_none());
+    }
+}
@@ -1,6 +1,6 @@
 
-use super::{App, SubCommand, ArgMatches, Arg, Operation, Comp, Write, File, PathBuf, valid_item,
-            item_exists};
+use super::{App, SubCommand, ArgMatches, Arg, Operation, Comp, Write, PathBuf,
+            item_exists, open_output, get_options, get_items};
 
 use std::io::BufWriter;
 
@@ -14,8 +14,7 @@ pub fn build<'a>() -> App<'static, 'a> {
                 .takes_value(true)
                 .multiple(true)
                 .value_name("FILE/DIR")
-                .required(true)
-                .validator(valid_item)
+                .required_unless("files-from")
                 .next_line_help(true)
                 .global(true)
                 .help("what to tar"),
@@ -30,23 +29,26 @@ pub fn build<'a>() -> App<'static, 'a> {
                 .required(true)
                 .validator(item_exists)
                 .global(true)
-                .help("tarball output"),
+                .help("tarball output, `-` writes stdout"),
         )
 }
 
 pub fn get(x: &ArgMatches) -> Operation {
+    let items = get_items(x);
+    let opts = get_options(x);
     Operation::Create(
         Comp::Tar({
             let path = x.value_of("output").unwrap();
-            match File::create(&path) {
+            match open_output(&path) {
                 Ok(x) => BufWriter::with_capacity(131072, x),
                 Err(e) => {
-                    println!("Could not create output {}", &path);
-                    println!("Error {:?}", e);
+                    eprintln!("Could not create output {}", &path);
+                    eprintln!("Error {:?}", e);
                     ::std::process::exit(1)
                 }
             }
         }),
-        x.values_of("file").unwrap().map(PathBuf::from).collect(),
+        items,
+        opts,
     )
 }
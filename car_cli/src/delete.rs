@@ -0,0 +1,81 @@
+
+use super::{io, Write, Operation, App, Arg, ArgMatches, SubCommand, Path, PathBuf, File, Regex,
+            Format, Comp, Decomp};
+use super::valid::{valid_path, valid_regex};
+use super::tarball::{self, Members};
+use super::staging;
+
+/// Build the `delete` subcommand
+pub fn build<'a>() -> App<'static, 'a> {
+    SubCommand::with_name("delete")
+        .about("Remove entries from an archive")
+        .arg(
+            Arg::with_name("file")
+                .index(1)
+                .takes_value(true)
+                .multiple(false)
+                .value_name("ARCHIVE")
+                .required(true)
+                .validator(valid_path)
+                .next_line_help(true)
+                .help("archive to remove entries from"),
+        )
+        .arg(
+            Arg::with_name("regex")
+                .short("r")
+                .long("regex")
+                .takes_value(true)
+                .multiple(false)
+                .value_name("REGEX")
+                .required(true)
+                .validator(valid_regex)
+                .next_line_help(true)
+                .help("Files which match REGEX will be removed"),
+        )
+}
+
+pub fn get(x: &ArgMatches) -> Operation {
+    Operation::Delete(
+        PathBuf::from(x.value_of("file").unwrap()),
+        Regex::new(x.value_of("regex").unwrap()).unwrap(),
+    )
+}
+
+/// Rewrite `archive` without the members matching `regex`
+///
+/// The new archive is built next to the old one, in the same format, and
+/// only renamed over it once it is complete.
+pub fn exec(archive: &Path, regex: &Regex) -> io::Result<()> {
+    let f = Format::from_path(archive)?;
+    let tmp = staging::sibling(archive);
+    match rewrite(archive, &tmp, f, regex) {
+        Ok(()) => staging::commit(&tmp, archive),
+        Err(e) => {
+            staging::discard(&tmp);
+            Err(e)
+        }
+    }
+}
+
+fn rewrite(archive: &Path, tmp: &Path, f: Format, regex: &Regex) -> io::Result<()> {
+    let mut members = Members::new(Decomp::from_unknown(File::open(archive)?)?);
+    let mut comp = Comp::from_format(f, File::create(tmp)?)?;
+    while let Option::Some(m) = members.next_member()? {
+        let matched = match m.path.file_name() {
+            Option::None => false,
+            Option::Some(f_name) => {
+                match f_name.to_str() {
+                    Option::None => false,
+                    Option::Some(f_name_str) => regex.is_match(f_name_str),
+                }
+            }
+        };
+        if !matched {
+            // raw headers keep long name, pax, and sparse records intact
+            comp.write_all(&m.raw)?;
+            members.copy_data(&mut comp)?;
+        }
+    }
+    comp.write_all(&[0u8; 1024])?;
+    tarball::finish(comp)?.sync_all()
+}
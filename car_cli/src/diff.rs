@@ -0,0 +1,157 @@
+
+use super::{io, Read, Operation, App, Arg, ArgMatches, SubCommand, PathBuf, Path, Entry, File};
+use super::valid::valid_input;
+use super::tarball::fill;
+
+use std::fs::{self, Metadata};
+use std::time::UNIX_EPOCH;
+
+fn valid_dir(x: String) -> Result<(), String> {
+    let p = PathBuf::from(&x);
+    match (p.exists(), p.is_dir()) {
+        (true, true) => Ok(()),
+        (false, _) => Err(format!("Cannot compare to {} it does not exist", &x)),
+        (true, false) => Err(format!("Cannot compare to {} it is file or link", &x)),
+    }
+}
+
+/// Build the `diff` subcommand
+pub fn build<'a>() -> App<'static, 'a> {
+    SubCommand::with_name("diff")
+        .about("Compare the contents of a tar with the filesystem")
+        .arg(
+            Arg::with_name("file")
+                .index(1)
+                .takes_value(true)
+                .multiple(false)
+                .value_name("INFILE")
+                .required(true)
+                .validator(valid_input)
+                .next_line_help(true)
+                .help("file to read, `-` reads stdin"),
+        )
+        .arg(
+            Arg::with_name("dir")
+                .index(2)
+                .takes_value(true)
+                .multiple(false)
+                .value_name("DIR")
+                .validator(valid_dir)
+                .next_line_help(true)
+                .help("Directory to compare against, defaults to the current one"),
+        )
+}
+
+pub fn get(x: &ArgMatches) -> Operation {
+    Operation::Diff(
+        PathBuf::from(x.value_of("file").unwrap()),
+        match x.value_of("dir") {
+            Option::None => None,
+            Option::Some(d) => Some(PathBuf::from(d)),
+        },
+    )
+}
+
+/// Compare one entry with the filesystem
+///
+/// Every difference is printed, returns `true` if any were found.
+pub fn exec<R: Read>(entry: &mut Entry<R>, dir: &Option<PathBuf>) -> io::Result<bool> {
+    let name = entry.path()?.into_owned();
+    let path = match dir {
+        &Option::None => name.clone(),
+        &Option::Some(ref d) => d.join(&name),
+    };
+    let meta = match fs::symlink_metadata(&path) {
+        Ok(x) => x,
+        Err(e) => {
+            println!("{:?}: Missing on disk ({})", name, e);
+            return Ok(true);
+        }
+    };
+    let kind = entry.header().entry_type();
+    let same_kind = if kind.is_dir() {
+        meta.is_dir()
+    } else if kind.is_symlink() {
+        meta.file_type().is_symlink()
+    } else if kind.is_file() || kind.is_contiguous() || kind.is_gnu_sparse() {
+        meta.is_file()
+    } else {
+        // hardlinks and special files only have their existence checked
+        return Ok(false);
+    };
+    if !same_kind {
+        println!("{:?}: File type differs", name);
+        return Ok(true);
+    }
+    let mut differs = owner_differs(entry, &name, &meta)?;
+    if kind.is_symlink() {
+        let target = fs::read_link(&path)?;
+        if entry.link_name()?.map(|x| x.into_owned()) != Some(target) {
+            println!("{:?}: Symlink differs", name);
+            differs = true;
+        }
+        return Ok(differs);
+    }
+    if kind.is_dir() {
+        return Ok(differs);
+    }
+    let mtime = meta.modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    if mtime != entry.header().mtime()? {
+        println!("{:?}: Mod time differs", name);
+        differs = true;
+    }
+    if meta.len() != entry.header().size()? {
+        println!("{:?}: Size differs", name);
+        return Ok(true);
+    }
+    if !same_contents(entry, &mut File::open(&path)?)? {
+        println!("{:?}: Contents differ", name);
+        differs = true;
+    }
+    Ok(differs)
+}
+
+/// Mode, uid, and gid
+#[cfg(unix)]
+fn owner_differs<R: Read>(entry: &Entry<R>, name: &Path, meta: &Metadata) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    let header = entry.header();
+    let mut differs = false;
+    if !meta.file_type().is_symlink() && (meta.mode() & 0o7777) != (header.mode()? & 0o7777) {
+        println!("{:?}: Mode differs", name);
+        differs = true;
+    }
+    if u64::from(meta.uid()) != u64::from(header.uid()?) {
+        println!("{:?}: Uid differs", name);
+        differs = true;
+    }
+    if u64::from(meta.gid()) != u64::from(header.gid()?) {
+        println!("{:?}: Gid differs", name);
+        differs = true;
+    }
+    Ok(differs)
+}
+
+#[cfg(windows)]
+fn owner_differs<R: Read>(_: &Entry<R>, _: &Path, _: &Metadata) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// Streams both readers, never holding more than a buffer of each
+fn same_contents<A: Read, B: Read>(a: &mut A, b: &mut B) -> io::Result<bool> {
+    let mut x = vec![0u8; 65536];
+    let mut y = vec![0u8; 65536];
+    loop {
+        let n = fill(a, &mut x)?;
+        let m = fill(b, &mut y)?;
+        if n != m || x[..n] != y[..m] {
+            return Ok(false);
+        }
+        if n == 0 {
+            return Ok(true);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+
+use super::{io, Read, Path, PathBuf, Entry, Header, Options};
+
+extern crate filetime;
+use self::filetime::FileTime;
+
+#[cfg(unix)]
+extern crate xattr;
+#[cfg(unix)]
+use super::libc;
+
+/// Extended attributes stored as `SCHILY.xattr.*` pax records
+pub type Xattrs = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// What `apply` restores, this is copied to every extraction worker
+#[derive(Clone, Copy)]
+pub struct Restore {
+    pub xattrs: bool,
+    pub perms: bool,
+    pub touch: bool,
+    pub same_owner: bool,
+    pub umask: u32,
+}
+impl Restore {
+    pub fn new(opts: &Options) -> Restore {
+        Restore {
+            xattrs: opts.xattrs,
+            perms: opts.perms,
+            touch: opts.touch,
+            same_owner: opts.same_owner,
+            umask: umask(),
+        }
+    }
+}
+
+/// A directory whose metadata is applied once its contents exist
+pub struct Pending {
+    pub path: PathBuf,
+    pub header: Header,
+    pub xattrs: Xattrs,
+}
+
+/// Read the extended attributes of an entry
+pub fn xattrs<R: Read>(entry: &mut Entry<R>) -> io::Result<Xattrs> {
+    let prefix = b"SCHILY.xattr.";
+    let mut v = Vec::new();
+    let exts = match entry.pax_extensions()? {
+        Option::Some(x) => x,
+        Option::None => return Ok(v),
+    };
+    for ext in exts {
+        let ext = ext?;
+        let key = ext.key_bytes();
+        if key.starts_with(prefix) {
+            v.push((key[prefix.len()..].to_vec(), ext.value_bytes().to_vec()));
+        }
+    }
+    Ok(v)
+}
+
+/// Restore the metadata recorded in `header` onto `dest`
+///
+/// Symlinks only get their owner, everything else about a symlink is
+/// taken from its target. Modes are masked by `umask` unless `--perms`.
+pub fn apply(header: &Header, attrs: &Xattrs, dest: &Path, opts: &Restore) -> io::Result<()> {
+    let symlink = header.entry_type().is_symlink();
+    if opts.same_owner {
+        chown(header, dest)?;
+    }
+    if symlink {
+        return Ok(());
+    }
+    if opts.xattrs {
+        set_xattrs(attrs, dest);
+    }
+    chmod(header, dest, opts.perms, opts.umask)?;
+    if !opts.touch {
+        let t = FileTime::from_seconds_since_1970(header.mtime()?, 0);
+        filetime::set_file_times(dest, t, t)?;
+    }
+    Ok(())
+}
+
+/// Is the process running as root
+#[cfg(unix)]
+pub fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(windows)]
+pub fn is_root() -> bool {
+    false
+}
+
+/// The process umask, there is no way to read it without setting it
+#[cfg(unix)]
+fn umask() -> u32 {
+    unsafe {
+        let mask = libc::umask(0o022);
+        libc::umask(mask);
+        mask as u32
+    }
+}
+
+#[cfg(windows)]
+fn umask() -> u32 {
+    0
+}
+
+#[cfg(unix)]
+fn chown(header: &Header, dest: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    let cpath = match CString::new(dest.as_os_str().as_bytes()) {
+        Ok(x) => x,
+        Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidInput, e)),
+    };
+    let (uid, gid) = (u64::from(header.uid()?), u64::from(header.gid()?));
+    // `-1` asks lchown to leave the id alone, so it's out of range too
+    if uid >= libc::uid_t::max_value() as u64 || gid >= libc::gid_t::max_value() as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("uid {} or gid {} is out of range", uid, gid),
+        ));
+    }
+    let (uid, gid) = (uid as libc::uid_t, gid as libc::gid_t);
+    match unsafe { libc::lchown(cpath.as_ptr(), uid, gid) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(windows)]
+fn chown(_: &Header, _: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn chmod(header: &Header, dest: &Path, perms: bool, umask: u32) -> io::Result<()> {
+    use std::fs::{set_permissions, Permissions};
+    use std::os::unix::fs::PermissionsExt;
+    let mode = match perms {
+        true => header.mode()? & 0o7777,
+        false => header.mode()? & 0o777 & !umask,
+    };
+    set_permissions(dest, Permissions::from_mode(mode))
+}
+
+#[cfg(windows)]
+fn chmod(header: &Header, dest: &Path, _: bool, _: u32) -> io::Result<()> {
+    use std::fs::{metadata, set_permissions};
+    let mut p = metadata(dest)?.permissions();
+    p.set_readonly(header.mode()? & 0o222 == 0);
+    set_permissions(dest, p)
+}
+
+/// Filesystems without xattr support are common, so failures only warn
+#[cfg(unix)]
+fn set_xattrs(attrs: &Xattrs, dest: &Path) {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    for &(ref key, ref value) in attrs.iter() {
+        if let Err(e) = xattr::set(dest, OsStr::from_bytes(key), value) {
+            println!(
+                "Could not set xattr {} on {:?}: {}",
+                String::from_utf8_lossy(key),
+                dest,
+                e
+            );
+        }
+    }
+}
+
+#[cfg(windows)]
+fn set_xattrs(_: &Xattrs, _: &Path) {}
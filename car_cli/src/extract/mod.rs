@@ -0,0 +1,806 @@
+
+use super::{io, Read, Operation, App, Arg, ArgMatches, SubCommand, PathBuf, Path, Regex,
+            Archive, Entry, Header, print_size};
+use super::valid::{valid_input, valid_regex};
+use super::staging;
+use super::tarball;
+
+#[cfg(unix)]
+extern crate libc;
+
+mod meta;
+mod rename;
+mod sandbox;
+mod pool;
+mod sparse;
+use self::meta::{Pending, Restore};
+use self::pool::Pool;
+use self::rename::Transform;
+use self::sandbox::Budget;
+
+use std::io::{Write, BufWriter};
+use std::fs::{self, File};
+use std::time::UNIX_EPOCH;
+
+fn valid_number(x: String) -> Result<(), String> {
+    match x.parse::<usize>() {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("{} is not a count: {}", &x, e)),
+    }
+}
+fn valid_jobs(x: String) -> Result<(), String> {
+    match x.parse::<usize>() {
+        Ok(0) => Err(format!("At least one job is needed")),
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("{} is not a count: {}", &x, e)),
+    }
+}
+fn valid_size(x: String) -> Result<(), String> {
+    match x.parse::<u64>() {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("{} is not a number: {}", &x, e)),
+    }
+}
+fn valid_transform(x: String) -> Result<(), String> {
+    Transform::parse(&x).map(|_| ())
+}
+/// A missing directory is created as entries are extracted into it
+fn valid_dir(x: String) -> Result<(), String> {
+    let p = PathBuf::from(&x);
+    match (p.exists(), p.is_dir()) {
+        (true, true) | (false, _) => Ok(()),
+        (true, false) => Err(format!("Cannot extract to {} it is file or link", &x)),
+    }
+}
+
+/// Build the `extract` subcommand
+pub fn build<'a>() -> App<'static, 'a> {
+    SubCommand::with_name("extract")
+        .about("Extract contents of a tar")
+        .arg(
+            Arg::with_name("file")
+                .index(1)
+                .takes_value(true)
+                .multiple(false)
+                .value_name("INFILE")
+                .required(true)
+                .validator(valid_input)
+                .next_line_help(true)
+                .help("file to read, `-` reads stdin"),
+        )
+        .arg(
+            Arg::with_name("regex")
+                .short("r")
+                .long("regex")
+                .takes_value(true)
+                .multiple(false)
+                .value_name("REGEX")
+                .validator(valid_regex)
+                .next_line_help(true)
+                .help("Only files which match REGEX will be extracted"),
+        )
+        .arg(
+            Arg::with_name("out")
+                .index(2)
+                .takes_value(true)
+                .multiple(false)
+                .value_name("OUTPATH")
+                .validator(valid_dir)
+                .next_line_help(true)
+                .help("Where to extract too"),
+        )
+        .arg(
+            Arg::with_name("xattrs")
+                .long("xattrs")
+                .takes_value(false)
+                .next_line_help(true)
+                .help("Indicate to preserve xattrs (Unix only)"),
+        )
+        .arg(
+            Arg::with_name("perms")
+                .long("perms")
+                .takes_value(false)
+                .next_line_help(true)
+                .help("Indicate to perserve perms (Unix only)"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .takes_value(false)
+                .next_line_help(true)
+                .help("Print each file extracted and how many bytes were written"),
+        )
+        .arg(
+            Arg::with_name("touch")
+                .short("m")
+                .long("touch")
+                .takes_value(false)
+                .next_line_help(true)
+                .help("Don't restore modification times, files are left as extracted"),
+        )
+        .arg(
+            Arg::with_name("same-owner")
+                .long("same-owner")
+                .takes_value(false)
+                .next_line_help(true)
+                .help("Restore owner and group from the archive (root only, Unix only)"),
+        )
+        .arg(
+            Arg::with_name("overwrite")
+                .long("overwrite")
+                .takes_value(false)
+                .conflicts_with_all(&["keep-old-files", "skip-old-files", "keep-newer-files"])
+                .next_line_help(true)
+                .help("Overwrite existing files (the default)"),
+        )
+        .arg(
+            Arg::with_name("keep-old-files")
+                .short("k")
+                .long("keep-old-files")
+                .takes_value(false)
+                .conflicts_with_all(&["overwrite", "skip-old-files", "keep-newer-files"])
+                .next_line_help(true)
+                .help("Fail if a file already exists"),
+        )
+        .arg(
+            Arg::with_name("skip-old-files")
+                .long("skip-old-files")
+                .takes_value(false)
+                .conflicts_with_all(&["overwrite", "keep-old-files", "keep-newer-files"])
+                .next_line_help(true)
+                .help("Leave existing files alone, without complaint"),
+        )
+        .arg(
+            Arg::with_name("keep-newer-files")
+                .long("keep-newer-files")
+                .takes_value(false)
+                .conflicts_with_all(&["overwrite", "keep-old-files", "skip-old-files"])
+                .next_line_help(true)
+                .help("Leave existing files alone if they are newer than their archived copy"),
+        )
+        .arg(
+            Arg::with_name("unlink-first")
+                .short("U")
+                .long("unlink-first")
+                .takes_value(false)
+                .next_line_help(true)
+                .help(
+                    "Remove existing files before extracting over them, rather than \
+                     writing through symlinks",
+                ),
+        )
+        .arg(
+            Arg::with_name("unsafe-paths")
+                .long("unsafe-paths")
+                .takes_value(false)
+                .next_line_help(true)
+                .help(
+                    "Allow absolute paths, `..`, and writing through symlinks which \
+                     lead outside of OUTPATH",
+                ),
+        )
+        .arg(
+            Arg::with_name("max-bytes")
+                .long("max-bytes")
+                .takes_value(true)
+                .multiple(false)
+                .value_name("BYTES")
+                .validator(valid_size)
+                .next_line_help(true)
+                .help("Stop if the entries extracted hold more than BYTES"),
+        )
+        .arg(
+            Arg::with_name("max-entries")
+                .long("max-entries")
+                .takes_value(true)
+                .multiple(false)
+                .value_name("N")
+                .validator(valid_size)
+                .next_line_help(true)
+                .help("Stop if more than N entries would be extracted"),
+        )
+        .arg(
+            Arg::with_name("atomic")
+                .long("atomic")
+                .takes_value(false)
+                .requires("out")
+                .next_line_help(true)
+                .help(
+                    "Extract into a temporary directory beside OUTPATH, and only \
+                     move it into place once the whole archive was read without \
+                     error. OUTPATH must be missing or empty",
+                ),
+        )
+        .arg(
+            Arg::with_name("jobs")
+                .short("j")
+                .long("jobs")
+                .takes_value(true)
+                .multiple(false)
+                .value_name("N")
+                .validator(valid_jobs)
+                .next_line_help(true)
+                .help(
+                    "Create files on N threads. Decompression stays on one \
+                     thread, and at most 64MiB of file data is held in memory",
+                ),
+        )
+        .arg(
+            Arg::with_name("strip-components")
+                .long("strip-components")
+                .takes_value(true)
+                .multiple(false)
+                .value_name("N")
+                .validator(valid_number)
+                .next_line_help(true)
+                .help("Remove the first N leading components from each path"),
+        )
+        .arg(
+            Arg::with_name("transform")
+                .long("transform")
+                .takes_value(true)
+                .multiple(false)
+                .value_name("s/REGEX/REPL/FLAGS")
+                .validator(valid_transform)
+                .next_line_help(true)
+                .help(
+                    "Rewrite each path (after --strip-components) with a sed style \
+                     replacement. Entries left with an empty path are skipped",
+                ),
+        )
+}
+
+/// What to do when an entry's path already exists
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Existing {
+    Overwrite,
+    Keep,
+    Skip,
+    KeepNewer,
+}
+
+fn get_existing(x: &ArgMatches) -> Existing {
+    if x.is_present("keep-old-files") {
+        return Existing::Keep;
+    }
+    if x.is_present("skip-old-files") {
+        return Existing::Skip;
+    }
+    if x.is_present("keep-newer-files") {
+        return Existing::KeepNewer;
+    }
+    Existing::Overwrite
+}
+
+/// Flags which change how entries are written to disk
+pub struct Options {
+    pub xattrs: bool,
+    pub perms: bool,
+    pub verbose: bool,
+    pub touch: bool,
+    pub same_owner: bool,
+    pub strip: usize,
+    pub transform: Option<Transform>,
+    pub existing: Existing,
+    pub unlink_first: bool,
+    pub unsafe_paths: bool,
+    pub max_bytes: Option<u64>,
+    pub max_entries: Option<u64>,
+    pub atomic: bool,
+    pub jobs: usize,
+}
+
+/// Carried from one entry to the next
+struct State {
+    root: PathBuf,
+    restore: Restore,
+    dirs: Vec<Pending>,
+    budget: Budget,
+    pool: Option<Pool>,
+}
+
+/// Extract every entry of `archive` which matches `regex`
+///
+/// Directory metadata is applied once the whole archive has been read so
+/// writing a directory's contents doesn't disturb its mtime, or trip over
+/// a read-only mode.
+pub fn run<R: Read>(
+    archive: Archive<R>,
+    regex: &Option<Regex>,
+    to: &Option<PathBuf>,
+    opts: &Options,
+) -> io::Result<()> {
+    match (to, opts.atomic) {
+        (&Option::Some(ref dest), true) => atomic(archive, regex, dest, opts),
+        (&Option::Some(ref dest), false) => extract_all(archive, regex, dest.clone(), opts),
+        (&Option::None, _) => extract_all(archive, regex, PathBuf::new(), opts),
+    }
+}
+
+/// Extract into a staging directory next to `dest` and rename it over
+/// `dest` once everything, the compressor's checksums included, checks out
+fn atomic<R: Read>(
+    archive: Archive<R>,
+    regex: &Option<Regex>,
+    dest: &Path,
+    opts: &Options,
+) -> io::Result<()> {
+    if dest.is_dir() && fs::read_dir(dest)?.next().is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{:?} is not empty, --atomic can't replace it", dest),
+        ));
+    }
+    let tmp = staging::sibling(dest);
+    fs::create_dir(&tmp)?;
+    match extract_all(archive, regex, tmp.clone(), opts) {
+        Ok(()) => {
+            if let Err(e) = staging::commit(&tmp, dest) {
+                staging::discard_dir(&tmp);
+                return Err(e);
+            }
+            Ok(())
+        }
+        Err(e) => {
+            staging::discard_dir(&tmp);
+            Err(e)
+        }
+    }
+}
+
+fn extract_all<R: Read>(
+    archive: Archive<R>,
+    regex: &Option<Regex>,
+    root: PathBuf,
+    opts: &Options,
+) -> io::Result<()> {
+    let mut archive = archive;
+    let restore = Restore::new(opts);
+    let mut state = State {
+        root: root,
+        restore: restore,
+        dirs: Vec::new(),
+        budget: Budget::new(),
+        pool: match opts.jobs {
+            0 | 1 => None,
+            n => Some(Pool::new(n, restore)),
+        },
+    };
+    for e in archive.entries()? {
+        let mut e = e?;
+        exec(&mut e, regex, opts, &mut state)?;
+    }
+    if let Option::Some(pool) = state.pool.take() {
+        pool.finish()?;
+    }
+    if opts.atomic {
+        // the rest of the stream holds the compressor's trailing checksums
+        io::copy(&mut archive.into_inner(), &mut io::sink())?;
+    }
+    // children were extracted after their parents, so undo them first
+    for d in state.dirs.iter().rev() {
+        meta::apply(&d.header, &d.xattrs, &d.path, &state.restore)?;
+    }
+    Ok(())
+}
+
+/// Extraction logic
+fn exec<R: Read>(
+    entry: &mut Entry<R>,
+    regex: &Option<Regex>,
+    opts: &Options,
+    state: &mut State,
+) -> io::Result<()> {
+    let flag = match regex {
+        &Option::None => true,
+        &Option::Some(ref regex) => {
+            let path = entry.path()?;
+            match path.file_name() {
+                Option::None => false,
+                Option::Some(f_name) => {
+                    match f_name.to_str() {
+                        Option::None => false,
+                        Option::Some(f_name_str) => regex.is_match(f_name_str),
+                    }
+                }
+            }
+        }
+    };
+    if flag {
+        match unpack(entry, opts, state)? {
+            Option::Some(written) if opts.verbose => {
+                // the path as written, after `--strip-components` and `--transform`
+                let path = renamed(archived_path(entry)?, opts)?.unwrap_or_default();
+                println!("{} {}", display_path(&path), print_size(written));
+            }
+            _ => {}
+        };
+    }
+    Ok(())
+}
+/// Get entry path, names which aren't UTF-8 are kept as they are
+#[cfg(unix)]
+fn archived_path<R: Read>(entry: &Entry<R>) -> io::Result<PathBuf> {
+    let bytes: Vec<u8> = entry
+        .path_bytes()
+        .iter()
+        .map(|b| if *b == b'\\' { b'/' } else { *b })
+        .collect();
+    Ok(tarball::bytes_to_path(&bytes))
+}
+
+#[cfg(windows)]
+fn archived_path<R: Read>(entry: &Entry<R>) -> io::Result<PathBuf> {
+    Ok(PathBuf::from(entry.path()?.to_string_lossy().replace("/", "\\")))
+}
+
+/// A path for printing
+fn display_path(path: &Path) -> String {
+    #[cfg(unix)]
+    {
+        path.to_string_lossy().replace("\\", "/")
+    }
+    #[cfg(windows)]
+    {
+        path.to_string_lossy().replace("/", "\\")
+    }
+}
+/// Check a path stored in the archive and strip its `.` components
+///
+/// Absolute paths, and paths which climb out with `..`, are rejected
+/// unless `--unsafe-paths` was given
+fn relative<P: AsRef<Path>>(path: P, unsafe_paths: bool) -> io::Result<PathBuf> {
+    use std::path::Component;
+    use std::io::{Error, ErrorKind};
+    let mut complete = PathBuf::new();
+    for c in path.as_ref().components() {
+        match c {
+            Component::CurDir => continue,
+            Component::Prefix(_) |
+            Component::RootDir |
+            Component::ParentDir if unsafe_paths => complete.push(c.as_os_str()),
+            Component::Prefix(_) |
+            Component::RootDir => {
+                return Err(Error::new(ErrorKind::InvalidData, "Must be relative path"))
+            }
+            Component::ParentDir => return Err(Error::new(ErrorKind::InvalidData, "URHAXSUX")),
+            Component::Normal(osstr) => complete.push(osstr),
+        };
+    }
+    Ok(complete)
+}
+
+/// Where an archived path lands under the root once `--strip-components`
+/// and `--transform` are applied. `None` if nothing is left of it.
+///
+/// Parent directories which are symlinks are resolved, refusing any which
+/// lead out of `root`, so the result can be written without following one.
+fn dest_path<P: AsRef<Path>>(path: P, root: &Path, opts: &Options) -> io::Result<Option<PathBuf>> {
+    let path = match renamed(path, opts)? {
+        Option::Some(p) => p,
+        Option::None => return Ok(None),
+    };
+    if opts.unsafe_paths {
+        Ok(Some(path))
+    } else {
+        Ok(Some(sandbox::resolve(root, &path)?))
+    }
+}
+
+/// An archived path after `--strip-components` and `--transform`, relative
+/// to the root. `None` if nothing is left of it.
+fn renamed<P: AsRef<Path>>(path: P, opts: &Options) -> io::Result<Option<PathBuf>> {
+    let unsafe_paths = opts.unsafe_paths;
+    match rename::rename(&relative(path, unsafe_paths)?, opts.strip, &opts.transform) {
+        Option::Some(p) => Ok(Some(relative(p, unsafe_paths)?)),
+        Option::None => Ok(None),
+    }
+}
+
+/// Create the parent directories of `path` under `root`
+fn build_path(root: &Path, path: &Path) -> io::Result<PathBuf> {
+    use std::fs::create_dir_all;
+    use std::io::{Error, ErrorKind};
+    if path.as_os_str().is_empty() {
+        return Err(Error::new(ErrorKind::InvalidData, "Path has no length"));
+    }
+    let complete = root.join(path);
+    match complete.parent() {
+        Option::Some(p) if !p.as_os_str().is_empty() => create_dir_all(p)?,
+        _ => {}
+    };
+    Ok(complete)
+}
+
+/// Apply the overwrite policy to whatever is already at `dest`
+///
+/// Returns `false` if the entry should be skipped.
+fn may_replace(dest: &Path, header: &Header, opts: &Options) -> io::Result<bool> {
+    let meta = match fs::symlink_metadata(dest) {
+        Ok(x) => x,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(e),
+    };
+    match opts.existing {
+        Existing::Overwrite => {}
+        Existing::Skip => return Ok(false),
+        Existing::Keep => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{:?} already exists", dest),
+            ))
+        }
+        Existing::KeepNewer => {
+            let on_disk = meta.modified()?
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            if on_disk > header.mtime()? {
+                return Ok(false);
+            }
+        }
+    };
+    // without this a symlink planted by an earlier entry could redirect
+    // the write anywhere
+    if opts.unlink_first || (!opts.unsafe_paths && meta.file_type().is_symlink()) {
+        remove_existing(dest)?;
+    }
+    Ok(true)
+}
+
+/// Anything other than a directory in the way of a new link or node is
+/// removed first, as `tar` does
+fn remove_existing(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(ref m) if !m.is_dir() => fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+/// This handles unpacking
+///
+/// The entry's type decides what is created. Regular files are streamed to
+/// disk through a fixed size buffer so memory use doesn't depend on how
+/// large the entry is. With `--jobs` files which fit in the pool's budget
+/// are read into memory and written by a worker instead. Returns the bytes
+/// written, or `None` if the entry was skipped.
+fn unpack<R: Read>(
+    entry: &mut Entry<R>,
+    opts: &Options,
+    state: &mut State,
+) -> io::Result<Option<u64>> {
+    let root = state.root.clone();
+    let restore = state.restore;
+    let path = match dest_path(archived_path(entry)?, &root, opts)? {
+        Option::Some(p) => p,
+        Option::None => return Ok(None),
+    };
+    let kind = entry.header().entry_type();
+    // sparse entries are charged what they expand to, not what they store
+    state.budget.charge(entry.header().size()?, opts)?;
+    let xattrs = match opts.xattrs {
+        true => meta::xattrs(entry)?,
+        false => Vec::new(),
+    };
+    if kind.is_dir() {
+        // `./` is the extraction root itself, which is left alone
+        if !path.as_os_str().is_empty() {
+            let dest = root.join(&path);
+            if let Option::Some(ref pool) = state.pool {
+                pool.wait_for(&dest)?;
+            }
+            if !opts.unsafe_paths {
+                // a symlink where the directory belongs must not be followed
+                match fs::symlink_metadata(&dest) {
+                    Ok(ref m) if m.file_type().is_symlink() => fs::remove_file(&dest)?,
+                    _ => {}
+                };
+            }
+            fs::create_dir_all(&dest)?;
+            state.dirs.push(Pending {
+                path: dest,
+                header: entry.header().clone(),
+                xattrs: xattrs,
+            });
+        }
+        return Ok(Some(0));
+    }
+    let dest = build_path(&root, &path)?;
+    if let Option::Some(ref pool) = state.pool {
+        pool.wait_for(&dest)?;
+    }
+    if !may_replace(&dest, entry.header(), opts)? {
+        return Ok(None);
+    }
+    if kind.is_symlink() {
+        let target = link_target(entry)?;
+        remove_existing(&dest)?;
+        symlink(&target, &dest)?;
+        meta::apply(entry.header(), &xattrs, &dest, &restore)?;
+        return Ok(Some(0));
+    }
+    if kind.is_hard_link() {
+        // shares the inode, and so the metadata, of its target
+        let target = match dest_path(link_target(entry)?, &root, opts)? {
+            Option::Some(p) => root.join(p),
+            Option::None => {
+                println!("Skipping {:?}, its link target was stripped away", path);
+                return Ok(None);
+            }
+        };
+        // the target has to be on disk first
+        if let Option::Some(ref pool) = state.pool {
+            pool.wait_all()?;
+        }
+        remove_existing(&dest)?;
+        fs::hard_link(&target, &dest)?;
+        return Ok(Some(0));
+    }
+    if kind.is_fifo() || kind.is_character_special() || kind.is_block_special() {
+        remove_existing(&dest)?;
+        match make_node(entry.header(), &dest) {
+            Ok(()) => meta::apply(entry.header(), &xattrs, &dest, &restore)?,
+            Err(e) => println!("Could not create {:?}: {}", dest, e),
+        };
+        return Ok(Some(0));
+    }
+    if kind.is_gnu_sparse() {
+        let mut f = File::create(&dest)?;
+        let written = sparse::copy(entry, &mut f)?;
+        drop(f);
+        meta::apply(entry.header(), &xattrs, &dest, &restore)?;
+        return Ok(Some(written));
+    }
+    let size = entry.header().entry_size()?;
+    match state.pool {
+        Option::Some(ref pool) if size <= pool::BUDGET => {
+            pool.reserve(size)?;
+            let mut data = Vec::with_capacity(size as usize);
+            entry.read_to_end(&mut data)?;
+            let written = data.len() as u64;
+            pool.send(dest, entry.header().clone(), xattrs, data, size)?;
+            return Ok(Some(written));
+        }
+        _ => {}
+    };
+    let mut f = BufWriter::with_capacity(65536, File::create(&dest)?);
+    let written = io::copy(entry, &mut f)?;
+    f.flush()?;
+    drop(f);
+    meta::apply(entry.header(), &xattrs, &dest, &restore)?;
+    Ok(Some(written))
+}
+
+fn link_target<R: Read>(entry: &Entry<R>) -> io::Result<PathBuf> {
+    match entry.link_name()? {
+        Option::Some(p) => Ok(p.into_owned()),
+        Option::None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "link entry has no target",
+        )),
+    }
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, dest: &Path) -> io::Result<()> {
+    ::std::os::unix::fs::symlink(target, dest)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, dest: &Path) -> io::Result<()> {
+    ::std::os::windows::fs::symlink_file(target, dest)
+}
+
+/// Create a FIFO or device node. Devices generally need root.
+#[cfg(unix)]
+fn make_node(header: &Header, dest: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    let kind = header.entry_type();
+    let mode = (header.mode()? & 0o7777) as libc::mode_t;
+    let cpath = match CString::new(dest.as_os_str().as_bytes()) {
+        Ok(x) => x,
+        Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidInput, e)),
+    };
+    let ret = if kind.is_fifo() {
+        unsafe { libc::mkfifo(cpath.as_ptr(), mode) }
+    } else {
+        let major = header.device_major()?.unwrap_or(0);
+        let minor = header.device_minor()?.unwrap_or(0);
+        let fmt = if kind.is_block_special() {
+            libc::S_IFBLK
+        } else {
+            libc::S_IFCHR
+        };
+        unsafe { libc::mknod(cpath.as_ptr(), fmt | mode, makedev(major, minor)) }
+    };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(windows)]
+fn make_node(_: &Header, _: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "FIFOs and device nodes are not supported on Windows",
+    ))
+}
+
+/// glibc's encoding of a device number
+#[cfg(target_os = "linux")]
+fn makedev(major: u32, minor: u32) -> libc::dev_t {
+    let (major, minor) = (major as libc::dev_t, minor as libc::dev_t);
+    ((major & 0xfffff000) << 32) | ((major & 0xfff) << 8) | ((minor & 0xffffff00) << 12) |
+        (minor & 0xff)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn makedev(major: u32, minor: u32) -> libc::dev_t {
+    ((major << 24) | minor) as libc::dev_t
+}
+
+#[cfg(unix)]
+pub fn get(x: &ArgMatches) -> Operation {
+    Operation::Extract(
+        PathBuf::from(x.value_of("file").unwrap()),
+        match x.value_of("regex") {
+            Option::None => None,
+            Option::Some(r) => Regex::new(&r).ok(),
+        },
+        match x.value_of("out") {
+            Option::None => None,
+            Option::Some(o) => Some(PathBuf::from(o)),
+        },
+        Options {
+            xattrs: x.is_present("xattrs"),
+            perms: x.is_present("perms"),
+            verbose: x.is_present("verbose"),
+            touch: x.is_present("touch"),
+            same_owner: x.is_present("same-owner") && meta::is_root(),
+            strip: x.value_of("strip-components").map_or(0, |n| n.parse().unwrap()),
+            transform: x.value_of("transform").map(|t| Transform::parse(t).unwrap()),
+            existing: get_existing(x),
+            unlink_first: x.is_present("unlink-first"),
+            unsafe_paths: x.is_present("unsafe-paths"),
+            max_bytes: x.value_of("max-bytes").map(|n| n.parse().unwrap()),
+            max_entries: x.value_of("max-entries").map(|n| n.parse().unwrap()),
+            atomic: x.is_present("atomic"),
+            jobs: x.value_of("jobs").map_or(1, |n| n.parse().unwrap()),
+        },
+    )
+}
+
+#[cfg(windows)]
+pub fn get(x: &ArgMatches) -> Operation {
+    Operation::Extract(
+        PathBuf::from(x.value_of("file").unwrap()),
+        match x.value_of("regex") {
+            Option::None => None,
+            Option::Some(r) => Regex::new(&r).ok(),
+        },
+        match x.value_of("out") {
+            Option::None => None,
+            Option::Some(o) => Some(PathBuf::from(o)),
+        },
+        Options {
+            xattrs: false,
+            perms: false,
+            verbose: x.is_present("verbose"),
+            touch: x.is_present("touch"),
+            same_owner: false,
+            strip: x.value_of("strip-components").map_or(0, |n| n.parse().unwrap()),
+            transform: x.value_of("transform").map(|t| Transform::parse(t).unwrap()),
+            existing: get_existing(x),
+            unlink_first: x.is_present("unlink-first"),
+            unsafe_paths: x.is_present("unsafe-paths"),
+            max_bytes: x.value_of("max-bytes").map(|n| n.parse().unwrap()),
+            max_entries: x.value_of("max-entries").map(|n| n.parse().unwrap()),
+            atomic: x.is_present("atomic"),
+            jobs: x.value_of("jobs").map_or(1, |n| n.parse().unwrap()),
+        },
+    )
+}
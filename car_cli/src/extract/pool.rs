@@ -0,0 +1,178 @@
+
+use super::{io, Write, PathBuf, Path, File, Header};
+use super::meta::{self, Restore, Xattrs};
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, Condvar};
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::thread::{self, JoinHandle};
+
+/// Bytes of file data which may be waiting on the workers at once
+pub const BUDGET: u64 = 64 * 1048576;
+
+/// A file which is completely read, ready to be written out
+pub struct Job {
+    pub dest: PathBuf,
+    pub header: Header,
+    pub xattrs: Xattrs,
+    pub data: Vec<u8>,
+    reserved: u64,
+}
+
+struct Inflight {
+    bytes: u64,
+    paths: HashSet<PathBuf>,
+    error: Option<io::Error>,
+}
+
+struct Shared {
+    state: Mutex<Inflight>,
+    cond: Condvar,
+}
+
+/// Workers which create files, restore their metadata and fsync them
+///
+/// The archive is still decompressed and read by a single thread, only the
+/// syscalls needed to put each file on disk are spread out.
+pub struct Pool {
+    tx: Option<Sender<Job>>,
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+impl Pool {
+    pub fn new(jobs: usize, restore: Restore) -> Pool {
+        let (tx, rx) = channel::<Job>();
+        let rx = Arc::new(Mutex::new(rx));
+        let shared = Arc::new(Shared {
+            state: Mutex::new(Inflight {
+                bytes: 0,
+                paths: HashSet::new(),
+                error: None,
+            }),
+            cond: Condvar::new(),
+        });
+        let workers = (0..jobs)
+            .map(|_| {
+                let rx = rx.clone();
+                let shared = shared.clone();
+                thread::spawn(move || work(rx, shared, restore))
+            })
+            .collect();
+        Pool {
+            tx: Some(tx),
+            shared: shared,
+            workers: workers,
+        }
+    }
+
+    /// Wait until `size` more bytes fit in the budget
+    ///
+    /// A file larger than the whole budget waits for the pool to empty.
+    pub fn reserve(&self, size: u64) -> io::Result<()> {
+        let mut s = self.shared.state.lock().unwrap();
+        while s.error.is_none() && s.bytes != 0 && s.bytes + size > BUDGET {
+            s = self.shared.cond.wait(s).unwrap();
+        }
+        s.bytes += size;
+        match s.error.take() {
+            Option::Some(e) => Err(e),
+            Option::None => Ok(()),
+        }
+    }
+
+    /// Queue a file, `reserve` must have been called for `size` bytes
+    pub fn send(
+        &self,
+        dest: PathBuf,
+        header: Header,
+        xattrs: Xattrs,
+        data: Vec<u8>,
+        size: u64,
+    ) -> io::Result<()> {
+        self.shared.state.lock().unwrap().paths.insert(dest.clone());
+        let job = Job {
+            dest: dest,
+            header: header,
+            xattrs: xattrs,
+            data: data,
+            reserved: size,
+        };
+        match self.tx.as_ref().unwrap().send(job) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(io::Error::new(io::ErrorKind::Other, "extraction workers exited")),
+        }
+    }
+
+    /// Wait for a queued file at `dest` to be written, if there is one
+    pub fn wait_for(&self, dest: &Path) -> io::Result<()> {
+        let mut s = self.shared.state.lock().unwrap();
+        while s.error.is_none() && s.paths.contains(dest) {
+            s = self.shared.cond.wait(s).unwrap();
+        }
+        match s.error.take() {
+            Option::Some(e) => Err(e),
+            Option::None => Ok(()),
+        }
+    }
+
+    /// Wait for every queued file to be written
+    pub fn wait_all(&self) -> io::Result<()> {
+        let mut s = self.shared.state.lock().unwrap();
+        while s.error.is_none() && !s.paths.is_empty() {
+            s = self.shared.cond.wait(s).unwrap();
+        }
+        match s.error.take() {
+            Option::Some(e) => Err(e),
+            Option::None => Ok(()),
+        }
+    }
+
+    /// Write everything still queued and stop the workers
+    pub fn finish(mut self) -> io::Result<()> {
+        self.join();
+        match self.shared.state.lock().unwrap().error.take() {
+            Option::Some(e) => Err(e),
+            Option::None => Ok(()),
+        }
+    }
+
+    fn join(&mut self) {
+        self.tx = None;
+        for w in self.workers.drain(..) {
+            let _ = w.join();
+        }
+    }
+}
+impl Drop for Pool {
+    /// Nothing may still be writing once extraction has returned
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+fn work(rx: Arc<Mutex<Receiver<Job>>>, shared: Arc<Shared>, restore: Restore) {
+    loop {
+        let job = match rx.lock().unwrap().recv() {
+            Ok(x) => x,
+            Err(_) => return,
+        };
+        let res = write(&job, &restore);
+        let mut s = shared.state.lock().unwrap();
+        s.bytes -= job.reserved;
+        s.paths.remove(&job.dest);
+        if let Err(e) = res {
+            if s.error.is_none() {
+                s.error = Some(io::Error::new(e.kind(), format!("{:?}: {}", job.dest, e)));
+            }
+        }
+        shared.cond.notify_all();
+    }
+}
+
+fn write(job: &Job, restore: &Restore) -> io::Result<()> {
+    let mut f = File::create(&job.dest)?;
+    f.write_all(&job.data)?;
+    f.sync_all()?;
+    drop(f);
+    meta::apply(&job.header, &job.xattrs, &job.dest, restore)
+}
@@ -0,0 +1,111 @@
+
+use super::{Path, PathBuf, Regex};
+
+/// A sed style `s/REGEX/REPL/FLAGS` expression
+///
+/// Any character may follow `s` as the delimiter. `REGEX` uses the syntax
+/// of the `regex` crate, so groups are `(..)` not `\(..\)`. In `REPL` `&` is the
+/// whole match and `\1`..`\9` are groups. The flags are `g` to replace
+/// every match rather than the first, and `i` to ignore case.
+pub struct Transform {
+    regex: Regex,
+    repl: String,
+    global: bool,
+}
+impl Transform {
+    pub fn parse(x: &str) -> Result<Transform, String> {
+        let mut chars = x.chars();
+        match chars.next() {
+            Option::Some('s') => {}
+            _ => return Err(format!("Transform {} must start with `s`", x)),
+        };
+        let delim = match chars.next() {
+            Option::Some(c) if c != '\\' && !c.is_alphanumeric() => c,
+            _ => return Err(format!("Transform {} has no delimiter", x)),
+        };
+        let mut parts = vec![String::new()];
+        let mut escaped = false;
+        for c in chars {
+            let last = parts.len() - 1;
+            if escaped {
+                if c != delim {
+                    parts[last].push('\\');
+                }
+                parts[last].push(c);
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == delim {
+                parts.push(String::new());
+            } else {
+                parts[last].push(c);
+            }
+        }
+        if parts.len() != 3 || escaped {
+            return Err(format!("Transform {} should look like s/REGEX/REPL/", x));
+        }
+        let mut global = false;
+        let mut pattern = parts[0].clone();
+        for f in parts[2].chars() {
+            match f {
+                'g' => global = true,
+                'i' => pattern = format!("(?i){}", pattern),
+                _ => return Err(format!("Transform flag {} is not supported", f)),
+            };
+        }
+        let regex = match Regex::new(&pattern) {
+            Ok(x) => x,
+            Err(e) => return Err(format!("Transform regex error: {}", e)),
+        };
+        Ok(Transform {
+            regex: regex,
+            repl: replacement(&parts[1]),
+            global: global,
+        })
+    }
+
+    pub fn apply(&self, x: &str) -> String {
+        match self.global {
+            true => self.regex.replace_all(x, self.repl.as_str()).into_owned(),
+            false => self.regex.replace(x, self.repl.as_str()).into_owned(),
+        }
+    }
+}
+
+/// Translate sed's replacement syntax into the `regex` crate's
+fn replacement(x: &str) -> String {
+    let mut s = String::with_capacity(x.len());
+    let mut chars = x.chars();
+    while let Option::Some(c) = chars.next() {
+        match c {
+            '&' => s.push_str("${0}"),
+            '$' => s.push_str("$$"),
+            '\\' => {
+                match chars.next() {
+                    Option::Some(d) if d.is_digit(10) => s.push_str(&format!("${{{}}}", d)),
+                    Option::Some(d) => s.push(d),
+                    Option::None => s.push('\\'),
+                };
+            }
+            _ => s.push(c),
+        };
+    }
+    s
+}
+
+/// Drop the first `strip` components of `path` then apply `transform`
+///
+/// Paths which aren't UTF-8 can't be matched, they're only stripped.
+/// Returns `None` if nothing is left of the path.
+pub fn rename(path: &Path, strip: usize, transform: &Option<Transform>) -> Option<PathBuf> {
+    let stripped: PathBuf = path.components().skip(strip).collect();
+    let renamed = match (transform, stripped.to_str()) {
+        (&Option::Some(ref t), Option::Some(s)) => PathBuf::from(t.apply(s)),
+        _ => stripped,
+    };
+    if renamed.as_os_str().is_empty() {
+        None
+    } else {
+        Some(renamed)
+    }
+}
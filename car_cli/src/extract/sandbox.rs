@@ -0,0 +1,102 @@
+
+use super::{io, Path, PathBuf, Options};
+
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::Component;
+
+/// Symlinks followed while resolving one path before giving up, like `ELOOP`
+const MAX_HOPS: usize = 40;
+
+/// Resolve the parent directories of `path` beneath `root`
+///
+/// Symlinks already on disk, or extracted earlier from the archive, are
+/// followed only while they stay inside `root`. The result is relative to
+/// `root` and contains no symlinks, except perhaps its last component.
+pub fn resolve(root: &Path, path: &Path) -> io::Result<PathBuf> {
+    let mut todo: Vec<OsString> = path.components()
+        .rev()
+        .map(|c| c.as_os_str().to_os_string())
+        .collect();
+    let mut done = PathBuf::new();
+    let mut hops = 0;
+    while let Option::Some(c) = todo.pop() {
+        if c == OsStr::new("..") {
+            if !done.pop() {
+                return Err(escape(path));
+            }
+            continue;
+        }
+        if todo.is_empty() {
+            done.push(c);
+            break;
+        }
+        let candidate = done.join(&c);
+        let is_link = match fs::symlink_metadata(root.join(&candidate)) {
+            Ok(m) => m.file_type().is_symlink(),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => false,
+            Err(e) => return Err(e),
+        };
+        if !is_link {
+            done = candidate;
+            continue;
+        }
+        hops += 1;
+        if hops > MAX_HOPS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{:?} has too many levels of symlinks", path),
+            ));
+        }
+        // the target replaces the link, relative to the link's directory
+        let target = fs::read_link(root.join(&candidate))?;
+        for t in target.components().rev() {
+            match t {
+                Component::Normal(x) => todo.push(x.to_os_string()),
+                Component::ParentDir => todo.push(OsString::from("..")),
+                Component::CurDir => {}
+                Component::RootDir |
+                Component::Prefix(_) => return Err(escape(path)),
+            };
+        }
+    }
+    Ok(done)
+}
+
+/// Totals checked against `--max-bytes` and `--max-entries`
+pub struct Budget {
+    bytes: u64,
+    entries: u64,
+}
+impl Budget {
+    pub fn new() -> Budget {
+        Budget {
+            bytes: 0,
+            entries: 0,
+        }
+    }
+
+    /// Account for one more entry holding `size` bytes
+    pub fn charge(&mut self, size: u64, opts: &Options) -> io::Result<()> {
+        self.bytes += size;
+        self.entries += 1;
+        match (opts.max_bytes, opts.max_entries) {
+            (Option::Some(max), _) if self.bytes > max => Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("Archive holds more than {} bytes (--max-bytes)", max),
+            )),
+            (_, Option::Some(max)) if self.entries > max => Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("Archive holds more than {} entries (--max-entries)", max),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn escape(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        format!("{:?} leads outside of the extraction directory", path),
+    )
+}
@@ -0,0 +1,34 @@
+
+use super::{io, Read, Write, File};
+use super::tarball::fill;
+
+use std::io::{Seek, SeekFrom};
+
+/// Runs of zeros this long, and this aligned, become holes
+const BLOCK: usize = 4096;
+
+/// Write a sparse entry to `f`, seeking over zeros rather than writing them
+///
+/// The archive only lists where the data is, so the holes come back out of
+/// the reader as zeros. `f` must be new, so what's skipped reads as zero.
+pub fn copy<R: Read>(r: &mut R, f: &mut File) -> io::Result<u64> {
+    let mut buf = vec![0u8; 16 * BLOCK];
+    let mut pos = 0;
+    loop {
+        let n = fill(r, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        for chunk in buf[..n].chunks(BLOCK) {
+            if chunk.iter().all(|b| *b == 0) {
+                f.seek(SeekFrom::Current(chunk.len() as i64))?;
+            } else {
+                f.write_all(chunk)?;
+            }
+        }
+        pos += n as u64;
+    }
+    // a file which ends in a hole
+    f.set_len(pos)?;
+    Ok(pos)
+}
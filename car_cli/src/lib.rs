@@ -1,6 +1,6 @@
 
 extern crate tar;
-pub use self::tar::{Archive, Entries, Entry, Header, Builder};
+pub use self::tar::{Archive, Entries, Entry, EntryType, Header, Builder};
 
 extern crate clap;
 pub use self::clap::{App, Arg, ArgMatches, SubCommand};
@@ -18,6 +18,17 @@ use car_compress::{Quality, Format, Comp, Decomp};
 mod list;
 mod extract;
 mod create;
+mod append;
+mod update;
+mod diff;
+mod concat;
+mod delete;
+mod convert;
+mod test;
+mod cat;
+mod tarball;
+mod staging;
+mod valid;
 
 /// Formats a value in human readable
 /// this is a dirty hack and not efficient at all
@@ -40,6 +51,14 @@ fn fetch<'a>() -> ArgMatches<'a> {
         .subcommand(list::build())
         .subcommand(extract::build())
         .subcommand(create::build())
+        .subcommand(create::shared(append::build()))
+        .subcommand(create::shared(update::build()))
+        .subcommand(diff::build())
+        .subcommand(concat::build())
+        .subcommand(delete::build())
+        .subcommand(convert::build())
+        .subcommand(test::build())
+        .subcommand(cat::build())
         .get_matches()
 }
 fn build_ops(x: &ArgMatches) -> Operation {
@@ -55,16 +74,56 @@ fn build_ops(x: &ArgMatches) -> Operation {
         Option::Some(x) => return create::get(x),
         Option::None => {}
     };
-    println!("I didn't understand that");
-    println!("Try running `--help`");
+    match x.subcommand_matches("append") {
+        Option::Some(x) => return append::get(x),
+        Option::None => {}
+    };
+    match x.subcommand_matches("update") {
+        Option::Some(x) => return update::get(x),
+        Option::None => {}
+    };
+    match x.subcommand_matches("diff") {
+        Option::Some(x) => return diff::get(x),
+        Option::None => {}
+    };
+    match x.subcommand_matches("concat") {
+        Option::Some(x) => return concat::get(x),
+        Option::None => {}
+    };
+    match x.subcommand_matches("delete") {
+        Option::Some(x) => return delete::get(x),
+        Option::None => {}
+    };
+    match x.subcommand_matches("convert") {
+        Option::Some(x) => return convert::get(x),
+        Option::None => {}
+    };
+    match x.subcommand_matches("test") {
+        Option::Some(x) => return test::get(x),
+        Option::None => {}
+    };
+    match x.subcommand_matches("cat") {
+        Option::Some(x) => return cat::get(x),
+        Option::None => {}
+    };
+    eprintln!("I didn't understand that");
+    eprintln!("Try running `--help`");
     ::std::process::exit(1);
 }
 
 /// Describes what the program is doing
 pub enum Operation {
     List(PathBuf, Option<Regex>, bool, bool, bool, bool, bool, bool),
-    Extract(PathBuf, Option<Regex>, Option<PathBuf>, bool, bool),
-    Create(Comp<File>, Vec<PathBuf>),
+    Extract(PathBuf, Option<Regex>, Option<PathBuf>, extract::Options),
+    Create(Comp<Box<Write>>, Vec<PathBuf>, create::Options),
+    Append(PathBuf, Vec<PathBuf>, create::Options),
+    Update(PathBuf, Vec<PathBuf>, create::Options),
+    Diff(PathBuf, Option<PathBuf>),
+    Concat(Comp<Box<Write>>, Vec<PathBuf>, bool),
+    Delete(PathBuf, Regex),
+    Convert(PathBuf, Comp<Box<Write>>, bool),
+    Test(PathBuf),
+    Cat(PathBuf, PathBuf, Option<Regex>),
 }
 impl Operation {
     /// Construction from CLI
@@ -77,45 +136,89 @@ impl Operation {
     pub fn is_read_action(&self) -> bool {
         match self {
             &Operation::List(_, _, _, _, _, _, _, _) |
-            &Operation::Extract(_, _, _, _, _) => true,
+            &Operation::Extract(_, _, _, _) |
+            &Operation::Diff(_, _) |
+            &Operation::Cat(_, _, _) => true,
             _ => false,
         }
     }
 
     /// Are we writing?
-    #[inline(always)]
     pub fn is_write_action(&self) -> bool {
-        !self.is_read_action()
+        match self {
+            &Operation::Create(_, _, _) |
+            &Operation::Concat(_, _, _) |
+            &Operation::Convert(_, _, _) => true,
+            _ => false,
+        }
+    }
+
+    /// Does the operation open and write its own files?
+    pub fn is_standalone_action(&self) -> bool {
+        match self {
+            &Operation::Append(_, _, _) |
+            &Operation::Update(_, _, _) |
+            &Operation::Delete(_, _) |
+            &Operation::Test(_) => true,
+            _ => false,
+        }
     }
 
 
     /// Create the reader decompressor
-    pub fn build_reader(&self) -> io::Result<Decomp<File>> {
+    ///
+    /// An input of `-` is read from stdin. As that can't seek the format
+    /// is found by peeking at the start of the stream.
+    pub fn build_reader(&self) -> io::Result<Box<Read>> {
         match self {
             &Operation::List(ref p, _, _, _, _, _, _, _) |
-            &Operation::Extract(ref p, _, _, _, _) => {
+            &Operation::Extract(ref p, _, _, _) |
+            &Operation::Diff(ref p, _) |
+            &Operation::Cat(ref p, _, _) => {
+                if p == Path::new("-") {
+                    return Ok(Box::new(Decomp::from_stream(io::stdin())?));
+                }
                 let f = OpenOptions::new()
                     .read(true)
                     .write(false)
                     .create(false)
                     .open(p)?;
-                Decomp::from_unknown(f)
+                Ok(Box::new(Decomp::from_unknown(f)?))
             }
             _ => panic!("Cody you called build_reader on an compress job"),
         }
     }
 
     /// Does compression
-    pub fn do_compress(self) -> Result<BufWriter<File>, String> {
+    pub fn do_compress(self) -> Result<BufWriter<Box<Write>>, String> {
         match self {
-            Operation::Create(comp, items) => create::exec(comp, &items),
+            Operation::Create(comp, items, opts) => create::exec(comp, &items, &opts),
+            Operation::Concat(comp, inputs, dedupe) => concat::exec(comp, &inputs, dedupe),
+            Operation::Convert(input, comp, repack) => convert::exec(&input, comp, repack),
             _ => panic!("Cody you called compress on an extract/list op"),
         }
     }
 
+    /// Execute an operation which manages its own files
+    pub fn do_standalone(self) -> io::Result<()> {
+        match self {
+            Operation::Append(archive, items, opts) => append::exec(&archive, &items, &opts),
+            Operation::Update(archive, items, opts) => update::exec(&archive, &items, &opts),
+            Operation::Delete(archive, regex) => delete::exec(&archive, &regex),
+            Operation::Test(archive) => test::exec(&archive),
+            _ => panic!("Cody you called standalone on a read/compress op"),
+        }
+    }
+
     /// Execute a read operation
     pub fn do_read<R: Read>(&self, x: Archive<R>) -> io::Result<()> {
         let mut x = x;
+        if let &Operation::Extract(_, ref r, ref outdir, ref opts) = self {
+            return extract::run(x, r, outdir, opts);
+        }
+        let mut differs = false;
+        let mut found = false;
+        let mut seen = cat::Seen::new();
         let entries = x.entries()?;
         for e in entries {
             let mut e = e?;
@@ -124,12 +227,28 @@ impl Operation {
                     let header = e.header();
                     list::exec(header, r, group, user, gid, uid, size)?;
                 }
-                &Operation::Extract(_, ref r, ref outdir, xattrs, perms) => {
-                    extract::exec(&mut e, r, outdir, xattrs, perms)?;
+                &Operation::Diff(_, ref dir) => {
+                    differs |= diff::exec(&mut e, dir)?;
+                }
+                &Operation::Cat(ref archive, ref target, ref all) => {
+                    let matched = cat::exec(&mut e, archive, target, all, &mut seen)?;
+                    if matched && all.is_none() {
+                        return Ok(());
+                    }
+                    found |= matched;
                 }
                 _ => panic!("Cody you called do_read on an compress job"),
             };
         }
-        Ok(())
+        if differs {
+            ::std::process::exit(1);
+        }
+        match self {
+            &Operation::Cat(_, ref target, _) if !found => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{:?} not found in archive", target),
+            )),
+            _ => Ok(()),
+        }
     }
 }
@@ -1,28 +1,7 @@
 
 
-use super::{print_size, io, Header, Operation, App, Arg, ArgMatches, SubCommand, PathBuf, Regex,
-            RegexFault};
-
-fn valid_path(x: String) -> Result<(), String> {
-    let p = PathBuf::from(&x);
-    match (p.exists(), p.is_file()) {
-        (true, true) => Ok(()),
-        (false, _) => Err(format!("Cannot process {} it does not exist", &x)),
-        (true, false) => Err(format!("Cannot process {} it is a directory or link", &x)),
-    }
-}
-
-fn valid_regex(x: String) -> Result<(), String> {
-    match Regex::new(&x) {
-        Ok(_) => Ok(()),
-        Err(RegexFault::CompiledTooBig(val)) => Err(format!(
-            "Input regex is too large. Set size limit {:?}",
-            val
-        )),
-        Err(RegexFault::Syntax(s)) => Err(format!("Regex Syntax Error: {}", s)),
-        Err(_) => Err(format!("Regex Syntax Error. Source undocumented :(")),
-    }
-}
+use super::{print_size, io, Header, Operation, App, Arg, ArgMatches, SubCommand, PathBuf, Regex};
+use super::valid::{valid_input, valid_regex};
 
 pub fn build<'a>() -> App<'static, 'a> {
     SubCommand::with_name("list")
@@ -70,9 +49,9 @@ pub fn build<'a>() -> App<'static, 'a> {
                 .multiple(false)
                 .value_name("INFILE")
                 .required(true)
-                .validator(valid_path)
+                .validator(valid_input)
                 .next_line_help(true)
-                .help("file to read"),
+                .help("file to read, `-` reads stdin"),
         )
         .arg(
             Arg::with_name("regex")
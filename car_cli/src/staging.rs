@@ -0,0 +1,44 @@
+
+use super::{io, Path, PathBuf};
+
+use std::fs;
+
+/// A hidden path next to `path` on the same filesystem
+///
+/// Data is written here first, then renamed over the original so
+/// a failure part way through never leaves a damaged file behind.
+/// This works just as well for directories.
+pub fn sibling(path: &Path) -> PathBuf {
+    let name = match path.file_name() {
+        Option::Some(x) => x.to_string_lossy().into_owned(),
+        Option::None => "car".to_string(),
+    };
+    let tmp = format!(".{}.car-{}", name, ::std::process::id());
+    match path.parent() {
+        Option::Some(p) => p.join(tmp),
+        Option::None => PathBuf::from(tmp),
+    }
+}
+
+/// Move the staged file into place
+///
+/// A file being replaced keeps its permissions, the staged copy was
+/// created with the defaults.
+pub fn commit(tmp: &Path, dest: &Path) -> io::Result<()> {
+    if let Ok(meta) = fs::metadata(dest) {
+        if meta.is_file() {
+            fs::set_permissions(tmp, meta.permissions())?;
+        }
+    }
+    fs::rename(tmp, dest)
+}
+
+/// Remove the staged file, errors are ignored as there is nothing to do
+pub fn discard(tmp: &Path) {
+    let _ = fs::remove_file(tmp);
+}
+
+/// Remove a staged directory and everything in it
+pub fn discard_dir(tmp: &Path) {
+    let _ = fs::remove_dir_all(tmp);
+}
@@ -0,0 +1,357 @@
+
+use super::{io, Read, Write, Header, EntryType, Builder, Path, PathBuf, Comp};
+
+/// Every record in a tar stream is this many bytes
+pub const BLOCK: u64 = 512;
+
+/// A single archive member and every header record which describes it
+///
+/// `tar::Archive` folds GNU long names, pax extensions, and sparse maps
+/// into the entry it hands back. When rewriting an archive we want those
+/// records byte for byte, so `Members` walks the blocks itself.
+pub struct Member {
+    /// Every header block for this member, data of long name/pax records
+    /// included, exactly as it appeared in the archive
+    pub raw: Vec<u8>,
+    /// The header which owns the data
+    pub header: Header,
+    /// Member path with long name and pax records applied
+    pub path: PathBuf,
+    /// Link target, with long link and pax records applied
+    pub link: Option<PathBuf>,
+}
+
+/// Walks a tar stream one member at a time
+pub struct Members<R: Read> {
+    inner: R,
+    pos: u64,
+    pending: u64,
+    end: Option<u64>,
+    terminated: bool,
+}
+impl<R: Read> Members<R> {
+    pub fn new(r: R) -> Members<R> {
+        Members {
+            inner: r,
+            pos: 0,
+            pending: 0,
+            end: None,
+            terminated: false,
+        }
+    }
+
+    /// Bytes consumed from the stream so far
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// The underlying reader
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Was an end-of-archive marker found, rather than EOF
+    pub fn terminated(&self) -> bool {
+        self.terminated
+    }
+
+    /// Offset of the end-of-archive marker.
+    ///
+    /// This is where new members should be written if the archive is
+    /// extended. Only meaningful once `next_member` has returned `None`.
+    pub fn end(&self) -> u64 {
+        match self.end {
+            Option::Some(x) => x,
+            Option::None => self.pos,
+        }
+    }
+
+    /// Read the headers of the next member.
+    ///
+    /// If the data of the previous member was not consumed it is skipped.
+    /// Returns `None` at the end-of-archive marker or a clean EOF.
+    pub fn next_member(&mut self) -> io::Result<Option<Member>> {
+        self.skip_data()?;
+        if self.end.is_some() {
+            return Ok(None);
+        }
+        let mut raw = Vec::with_capacity(BLOCK as usize);
+        let mut long_name: Option<Vec<u8>> = None;
+        let mut pax_path: Option<Vec<u8>> = None;
+        let mut long_link: Option<Vec<u8>> = None;
+        let mut pax_link: Option<Vec<u8>> = None;
+        loop {
+            let mut header = Header::new_old();
+            if !self.read_block(header.as_mut_bytes())? {
+                self.end = Some(self.pos);
+                return if raw.is_empty() {
+                    Ok(None)
+                } else {
+                    Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "archive ended between a member's headers",
+                    ))
+                };
+            }
+            if header.as_bytes().iter().all(|x| *x == 0) {
+                self.end = Some(self.pos - BLOCK);
+                self.terminated = true;
+                // the second zero block is optional in practice
+                let mut trailer = [0u8; 512];
+                let _ = self.read_block(&mut trailer)?;
+                return if raw.is_empty() {
+                    Ok(None)
+                } else {
+                    Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "archive ended between a member's headers",
+                    ))
+                };
+            }
+            if !checksum_ok(&header) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("header checksum mismatch at offset {}", self.pos - BLOCK),
+                ));
+            }
+            raw.extend_from_slice(header.as_bytes());
+            let size = header.entry_size()?;
+            let kind = header.entry_type();
+            if kind.is_gnu_longname() || kind.is_gnu_longlink() ||
+                kind.is_pax_local_extensions()
+            {
+                let data = self.read_record(size, &mut raw)?;
+                if kind.is_gnu_longname() {
+                    long_name = Some(trim_nul(&data).to_vec());
+                } else if kind.is_gnu_longlink() {
+                    long_link = Some(trim_nul(&data).to_vec());
+                } else if kind.is_pax_local_extensions() {
+                    if let Option::Some(p) = pax_value(&data, b"path") {
+                        pax_path = Some(p);
+                    }
+                    if let Option::Some(p) = pax_value(&data, b"linkpath") {
+                        pax_link = Some(p);
+                    }
+                }
+                continue;
+            }
+            if kind.is_gnu_sparse() {
+                let mut extended = header.as_gnu().map(|x| x.is_extended()).unwrap_or(false);
+                while extended {
+                    let mut ext = [0u8; 512];
+                    if !self.read_block(&mut ext)? {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "archive ended inside a sparse map",
+                        ));
+                    }
+                    raw.extend_from_slice(&ext);
+                    extended = ext[504] == 1;
+                }
+            }
+            let path = match pax_path.or(long_name) {
+                Option::Some(bytes) => bytes_to_path(&bytes),
+                Option::None => header.path()?.into_owned(),
+            };
+            let link = match pax_link.or(long_link) {
+                Option::Some(bytes) => Some(bytes_to_path(&bytes)),
+                Option::None => header.link_name()?.map(|x| x.into_owned()),
+            };
+            self.pending = padded(size);
+            return Ok(Some(Member {
+                raw: raw,
+                header: header,
+                path: path,
+                link: link,
+            }));
+        }
+    }
+
+    /// Copy the data of the current member, padding included
+    pub fn copy_data<W: Write>(&mut self, w: &mut W) -> io::Result<u64> {
+        let want = self.pending;
+        self.pending = 0;
+        let got = io::copy(&mut (&mut self.inner).take(want), w)?;
+        self.pos += got;
+        if got != want {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "archive ended inside a member's data",
+            ));
+        }
+        Ok(got)
+    }
+
+    /// Read past the data of the current member
+    pub fn skip_data(&mut self) -> io::Result<()> {
+        if self.pending != 0 {
+            self.copy_data(&mut io::sink())?;
+        }
+        Ok(())
+    }
+
+    /// Returns `false` on a clean EOF, errors if only part of a block exists
+    fn read_block(&mut self, buf: &mut [u8]) -> io::Result<bool> {
+        let read = fill(&mut self.inner, buf)?;
+        self.pos += read as u64;
+        match read {
+            0 => Ok(false),
+            512 => Ok(true),
+            _ => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "archive ended inside a header block",
+            )),
+        }
+    }
+
+    /// Read the data of a long name/pax record, appending it to `raw`
+    fn read_record(&mut self, size: u64, raw: &mut Vec<u8>) -> io::Result<Vec<u8>> {
+        let start = raw.len();
+        self.pending = padded(size);
+        self.copy_data(raw)?;
+        Ok(raw[start..start + size as usize].to_vec())
+    }
+}
+
+/// Read until `buf` is full or EOF, returns how much was read
+pub fn fill<R: Read>(r: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match r.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        };
+    }
+    Ok(read)
+}
+
+/// Round up to a whole number of blocks
+pub fn padded(size: u64) -> u64 {
+    (size + (BLOCK - 1)) & !(BLOCK - 1)
+}
+
+/// Header checksum, computed with the checksum field as spaces
+pub fn checksum_ok(header: &Header) -> bool {
+    let bytes = header.as_bytes();
+    let sum = bytes[..148]
+        .iter()
+        .chain(&bytes[156..])
+        .fold(0u32, |a, b| a + (*b as u32)) + 8 * 32;
+    match header.cksum() {
+        Ok(x) => x == sum,
+        Err(_) => false,
+    }
+}
+
+/// Complete a compressor and flush everything to disk
+pub fn finish<W: Write>(c: Comp<W>) -> io::Result<W> {
+    let w = match c.finish() {
+        Ok(x) => x,
+        Err(e) => return Err(io::Error::new(io::ErrorKind::Other, e)),
+    };
+    Ok(w.into_inner()?)
+}
+
+fn trim_nul(x: &[u8]) -> &[u8] {
+    match x.iter().position(|b| *b == 0) {
+        Option::Some(i) => &x[..i],
+        Option::None => x,
+    }
+}
+
+/// Find `key` in a pax extension record
+fn pax_value(data: &[u8], key: &[u8]) -> Option<Vec<u8>> {
+    for line in data.split(|b| *b == b'\n') {
+        let kv = match line.iter().position(|b| *b == b' ') {
+            Option::Some(i) => &line[i + 1..],
+            Option::None => continue,
+        };
+        if let Option::Some(eq) = kv.iter().position(|b| *b == b'=') {
+            if &kv[..eq] == key {
+                return Some(kv[eq + 1..].to_vec());
+            }
+        }
+    }
+    None
+}
+
+#[cfg(unix)]
+pub fn bytes_to_path(x: &[u8]) -> PathBuf {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(OsStr::from_bytes(x))
+}
+
+#[cfg(windows)]
+pub fn bytes_to_path(x: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(x).replace("/", "\\"))
+}
+
+/// Append a member under `path`, linking to `link` if given
+///
+/// Names which do not fit in the header are written as GNU long name and
+/// long link records ahead of it, the same as `Builder::append_path` does.
+/// The checksum of `header` is filled in here.
+pub fn append_named<W: Write, R: Read>(
+    builder: &mut Builder<W>,
+    header: &mut Header,
+    path: &Path,
+    link: Option<&Path>,
+    data: R,
+) -> io::Result<()> {
+    if let Err(e) = header.set_path(path) {
+        let bytes = path_to_bytes(path)?;
+        if bytes.len() < header.as_old().name.len() {
+            return Err(e);
+        }
+        long_record(builder, b'L', &bytes)?;
+        let short = bytes_to_path(&bytes[..header.as_old().name.len()]);
+        header.set_path(&short)?;
+    }
+    if let Option::Some(link) = link {
+        if let Err(e) = header.set_link_name(link) {
+            let bytes = path_to_bytes(link)?;
+            if bytes.len() < header.as_old().linkname.len() {
+                return Err(e);
+            }
+            long_record(builder, b'K', &bytes)?;
+            let short = bytes_to_path(&bytes[..header.as_old().linkname.len()]);
+            header.set_link_name(&short)?;
+        }
+    }
+    header.set_cksum();
+    builder.append(header, data)
+}
+
+/// GNU `././@LongLink` record holding a name which didn't fit
+fn long_record<W: Write>(builder: &mut Builder<W>, kind: u8, name: &[u8]) -> io::Result<()> {
+    let mut header = Header::new_gnu();
+    header.as_gnu_mut().unwrap().name[..13].clone_from_slice(b"././@LongLink");
+    header.set_mode(0o644);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_mtime(0);
+    header.set_size((name.len() + 1) as u64);
+    header.set_entry_type(EntryType::new(kind));
+    header.set_cksum();
+    builder.append(&header, name.chain(&[0u8][..]))
+}
+
+#[cfg(unix)]
+fn path_to_bytes(x: &Path) -> io::Result<Vec<u8>> {
+    use std::os::unix::ffi::OsStrExt;
+    Ok(x.as_os_str().as_bytes().to_vec())
+}
+
+#[cfg(windows)]
+fn path_to_bytes(x: &Path) -> io::Result<Vec<u8>> {
+    match x.to_str() {
+        Option::Some(s) => Ok(s.replace("\\", "/").into_bytes()),
+        Option::None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "path was not valid unicode",
+        )),
+    }
+}
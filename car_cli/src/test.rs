@@ -0,0 +1,181 @@
+
+use super::{io, Read, Operation, App, Arg, ArgMatches, SubCommand, Path, PathBuf, File, Format,
+            Decomp};
+use super::valid::valid_path;
+use super::tarball::{Members, BLOCK};
+
+use std::cell::Cell;
+use std::io::{Seek, SeekFrom};
+use std::rc::Rc;
+
+/// Exit code when the compression layer fails to decode
+pub const COMPRESSION_CORRUPT: i32 = 2;
+/// Exit code when a tar header is damaged
+pub const TAR_CORRUPT: i32 = 3;
+/// Exit code when the archive stops part way through
+pub const TRUNCATED: i32 = 4;
+
+/// Build the `test` subcommand
+pub fn build<'a>() -> App<'static, 'a> {
+    SubCommand::with_name("test")
+        .about("Verify an archive is intact without extracting it")
+        .after_help(
+            "EXIT CODES:\n    0    archive is intact\n    1    archive could not be \
+             opened\n    2    compression layer is corrupt\n    3    tar layer is \
+             corrupt\n    4    archive is truncated",
+        )
+        .arg(
+            Arg::with_name("file")
+                .index(1)
+                .takes_value(true)
+                .multiple(false)
+                .value_name("INFILE")
+                .required(true)
+                .validator(valid_path)
+                .next_line_help(true)
+                .help("file to verify"),
+        )
+}
+
+pub fn get(x: &ArgMatches) -> Operation {
+    Operation::Test(PathBuf::from(x.value_of("file").unwrap()))
+}
+
+/// Notes when the file runs out, a decoder failing after that was cut short
+/// rather than fed bad data
+struct Input {
+    inner: File,
+    eof: Rc<Cell<bool>>,
+}
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            self.eof.set(true);
+        }
+        Ok(n)
+    }
+}
+impl Seek for Input {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+/// Remembers if an error came from the decompressor, and how far it got
+struct Watch<R: Read> {
+    inner: R,
+    failed: bool,
+    pos: u64,
+}
+impl<R: Read> Read for Watch<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.inner.read(buf) {
+            Ok(x) => {
+                self.pos += x as u64;
+                Ok(x)
+            }
+            Err(e) => {
+                self.failed = true;
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Decode the whole archive, exiting with a code describing the first fault
+///
+/// Every header checksum is verified and the data of every member is read
+/// which in turn has the decompressor verify its own checksums. Once the
+/// end-of-archive marker is found the rest of the stream is drained so
+/// trailing checksums (gzip CRC, xz check) are validated too.
+///
+/// Offsets are into the decompressed tar stream.
+pub fn exec(archive: &Path) -> io::Result<()> {
+    let plain = match Format::from_path(archive)? {
+        Format::Tar(_) => true,
+        _ => false,
+    };
+    let eof = Rc::new(Cell::new(false));
+    let input = Input {
+        inner: File::open(archive)?,
+        eof: eof.clone(),
+    };
+    let decomp = match Decomp::from_unknown(input) {
+        Ok(x) => x,
+        Err(e) => fail(compression_code(&e, eof.get()), 0, plain, &e),
+    };
+    // sniffing the format may have read to the end of a small file
+    eof.set(false);
+    let mut members = Members::new(Watch {
+        inner: decomp,
+        failed: false,
+        pos: 0,
+    });
+    let mut count = 0u64;
+    let walked = loop {
+        match members.next_member() {
+            Ok(Option::Some(_)) => count += 1,
+            Ok(Option::None) if !members.terminated() => {
+                break Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "no end-of-archive marker",
+                ))
+            }
+            Ok(Option::None) => break Ok(()),
+            Err(e) => break Err(e),
+        };
+    };
+    let walked = walked.and_then(|_| {
+        io::copy(members.get_mut(), &mut io::sink()).map(|_| ())
+    });
+    let e = match walked {
+        Ok(()) => {
+            println!("{:?}: OK, {} entries", archive, count);
+            return Ok(());
+        }
+        Err(e) => e,
+    };
+    let at = members.position();
+    // a damaged stream can decode to garbage which fails as a tar header
+    // before the decoder reaches a checksum, so let it finish to be sure
+    let e = match members.get_mut().failed {
+        true => e,
+        false => {
+            match io::copy(members.get_mut(), &mut io::sink()) {
+                Ok(_) => e,
+                Err(x) => x,
+            }
+        }
+    };
+    let watch = members.get_mut();
+    if watch.failed {
+        fail(compression_code(&e, eof.get()), watch.pos, plain, &e)
+    } else if e.kind() == io::ErrorKind::UnexpectedEof {
+        fail(TRUNCATED, at, plain, &e)
+    } else {
+        // the damaged header is the block which was just read
+        fail(TAR_CORRUPT, at.saturating_sub(BLOCK), plain, &e)
+    }
+}
+
+fn compression_code(e: &io::Error, eof: bool) -> i32 {
+    match eof || e.kind() == io::ErrorKind::UnexpectedEof {
+        true => TRUNCATED,
+        false => COMPRESSION_CORRUPT,
+    }
+}
+
+fn fail(code: i32, offset: u64, plain: bool, e: &io::Error) -> ! {
+    let what = match code {
+        COMPRESSION_CORRUPT => "Compression layer is corrupt",
+        TAR_CORRUPT => "Tar layer is corrupt",
+        _ => "Archive is truncated",
+    };
+    match plain {
+        true => println!("{} at offset {}", what, offset),
+        false => println!("{} at offset {} of the decompressed stream", what, offset),
+    };
+    println!("{}", e);
+    ::std::process::exit(code);
+}
@@ -0,0 +1,47 @@
+
+use super::{io, Operation, App, Arg, ArgMatches, SubCommand, Path, PathBuf};
+use super::valid::valid_path;
+use super::append;
+use super::create::{get_options, get_items, Options};
+
+/// Build the `update` subcommand
+pub fn build<'a>() -> App<'static, 'a> {
+    SubCommand::with_name("update")
+        .about("Add files which are newer than their copy in an existing archive")
+        .arg(
+            Arg::with_name("file")
+                .short("f")
+                .long("file")
+                .takes_value(true)
+                .multiple(true)
+                .value_name("FILE/DIR")
+                .required_unless("files-from")
+                .next_line_help(true)
+                .help("what to check for changes"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("out")
+                .takes_value(true)
+                .multiple(false)
+                .value_name("ARCHIVE")
+                .required(true)
+                .validator(valid_path)
+                .next_line_help(true)
+                .help("archive to update"),
+        )
+}
+
+pub fn get(x: &ArgMatches) -> Operation {
+    Operation::Update(
+        PathBuf::from(x.value_of("output").unwrap()),
+        get_items(x),
+        get_options(x),
+    )
+}
+
+/// Append every file which is new, or modified since it was archived
+pub fn exec(archive: &Path, items: &[PathBuf], opts: &Options) -> io::Result<()> {
+    append::extend(archive, items, opts, true)
+}
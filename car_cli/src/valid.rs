@@ -0,0 +1,32 @@
+
+use super::{PathBuf, Regex, RegexFault};
+
+/// An existing regular file, usually an archive
+pub fn valid_path(x: String) -> Result<(), String> {
+    let p = PathBuf::from(&x);
+    match (p.exists(), p.is_file()) {
+        (true, true) => Ok(()),
+        (false, _) => Err(format!("Cannot process {} it does not exist", &x)),
+        (true, false) => Err(format!("Cannot process {} it is a directory or link", &x)),
+    }
+}
+
+/// Like `valid_path`, but `-` reads stdin
+pub fn valid_input(x: String) -> Result<(), String> {
+    if x == "-" {
+        return Ok(());
+    }
+    valid_path(x)
+}
+
+pub fn valid_regex(x: String) -> Result<(), String> {
+    match Regex::new(&x) {
+        Ok(_) => Ok(()),
+        Err(RegexFault::CompiledTooBig(val)) => Err(format!(
+            "Input regex is too large. Set size limit {:?}",
+            val
+        )),
+        Err(RegexFault::Syntax(s)) => Err(format!("Regex Syntax Error: {}", s)),
+        Err(_) => Err(format!("Regex Syntax Error. Source undocumented :(")),
+    }
+}
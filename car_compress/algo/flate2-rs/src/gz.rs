@@ -59,6 +59,7 @@ pub struct Builder {
     filename: Option<CString>,
     comment: Option<CString>,
     mtime: u32,
+    os: Option<u8>,
 }
 
 /// A gzip streaming decoder
@@ -116,6 +117,7 @@ impl Builder {
             filename: None,
             comment: None,
             mtime: 0,
+            os: None,
         }
     }
 
@@ -125,6 +127,14 @@ impl Builder {
         self
     }
 
+    /// Configure the `operating_system` field in the gzip header.
+    ///
+    /// By default this is the OS the encoder was built for.
+    pub fn operating_system(mut self, os: u8) -> Builder {
+        self.os = Some(os);
+        self
+    }
+
     /// Configure the `extra` field in the gzip header.
     pub fn extra(mut self, extra: Vec<u8>) -> Builder {
         self.extra = Some(extra);
@@ -188,6 +198,7 @@ impl Builder {
             filename,
             comment,
             mtime,
+            os,
         } = self;
         let mut flg = 0;
         let mut header = vec![0u8; 10];
@@ -227,11 +238,14 @@ impl Builder {
             Compression::Fast => 4,
             _ => 0,
         };
-        header[9] = match env::consts::OS {
-            "linux" => 3,
-            "macos" => 7,
-            "win32" => 0,
-            _ => 255,
+        header[9] = match os {
+            Some(os) => os,
+            None => match env::consts::OS {
+                "linux" => 3,
+                "macos" => 7,
+                "win32" => 0,
+                _ => 255,
+            },
         };
         return header;
     }
@@ -1,7 +1,7 @@
 
 use super::Format;
 
-use std::io::{self, Read, Write, Seek, BufReader, BufWriter};
+use std::io::{self, Read, Write, Seek, BufReader, BufWriter, Chain, Cursor};
 
 use super::libbzip::Encode as BzEn;
 use super::libbzip::Decode as BzDec;
@@ -10,6 +10,7 @@ use super::libbrotli::Encode as BrEn;
 use super::libbrotli::Decode as BrDec;
 
 use super::libflate::Encode as GzEn;
+use super::libflate::Builder as GzBuilder;
 use super::libflate::Decode as GzDec;
 
 use super::libxz::Decode as XzDec;
@@ -95,6 +96,20 @@ impl<R: Read + Seek> Decomp<R> {
         }
     }
 }
+impl<R: Read> Decomp<Chain<Cursor<Vec<u8>>, R>> {
+    /// Read the stream to determine _how_ to decompress it.
+    ///
+    /// Unlike `from_unknown` this doesn't need to seek. The bytes used to
+    /// identify the stream are buffered and chained back in front of it, so
+    /// this works for stdin, pipes, and sockets.
+    ///
+    /// If the stream is not supported this will return `Err(InvalidInput)`
+    pub fn from_stream(r: R) -> io::Result<Decomp<Chain<Cursor<Vec<u8>>, R>>> {
+        let mut r = r;
+        let (f, peeked) = Format::from_peek(&mut r)?;
+        Decomp::from_known(f, Cursor::new(peeked).chain(r))
+    }
+}
 impl<R: Read> Decomp<R> {
     /// You already know what you are decompressing
     ///
@@ -274,4 +289,21 @@ impl<W: Write> Comp<W> {
             Format::Tar(_) => Ok(Comp::Tar(BufWriter::with_capacity(131072, w))),
         }
     }
+
+    /// Encodes from a format, recording nothing about when or where
+    ///
+    /// Gzip headers carry a timestamp and the operating system they were
+    /// written on, these are pinned to `0` and `255` (unknown). No other
+    /// format records either, so they are built as `from_format` would.
+    pub fn from_format_reproducible(f: Format, w: W) -> io::Result<Comp<W>> {
+        match f {
+            Format::Gzip(q) => Ok(Comp::Gzip(
+                GzBuilder::new().mtime(0).operating_system(255).write(
+                    BufWriter::with_capacity(131072, w),
+                    q.into_gz(),
+                ),
+            )),
+            f => Comp::from_format(f, w),
+        }
+    }
 }
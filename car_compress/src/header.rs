@@ -221,7 +221,7 @@ impl Format {
 
     /// Try to find out the format of a file
     ///
-    /// This will attempt to open the file at path and read the first 16bytes
+    /// This will attempt to open the file at path and read the first 512bytes
     /// matching that against a known magic number.
     ///
     /// If the file's type is unknown this method will return `Err(InvalidInput)`
@@ -231,37 +231,51 @@ impl Format {
             .write(false)
             .create(false)
             .open(p)?;
-        let mut v = Vec::with_capacity(16);
-        unsafe { v.set_len(10) };
-        f.read_exact(v.as_mut_slice())?;
-        let _ = f;
-        match what_format(v.as_slice()) {
-            Option::Some(f) => Ok(f),
-            Option::None => Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "Unsupported file type",
-            )),
-        }
+        Format::from_reader(&mut f)
     }
 
     /// A slightly more efficient way to find a file's type.
     ///
-    /// This method will seek to the start, read 16 bytes, then
+    /// This method will seek to the start, read 512 bytes, then
     /// seek back to the start. The goal of this is to avoid multiple open/close cycles
     ///
     /// If the file's type is unknown this method will return `Err(InvalidInput)`
     pub fn from_reader<R: Read + Seek>(r: &mut R) -> io::Result<Format> {
         let _ = r.seek(SeekFrom::Start(0))?;
-        let mut v = Vec::with_capacity(16);
-        unsafe { v.set_len(16) };
-        let input = r.read(v.as_mut_slice())?;
+        let mut v = Vec::with_capacity(512);
+        let _ = (&mut *r).take(512).read_to_end(&mut v)?;
         let _ = r.seek(SeekFrom::Start(0))?;
-        unsafe { v.set_len(input) }
         match what_format(v.as_slice()) {
             Option::Some(f) => Ok(f),
             Option::None => {
                 let kind = io::ErrorKind::InvalidInput;
-                let msg = format!("Could not identify magic number {:?}", v.as_slice());
+                let msg = format!(
+                    "Could not identify magic number {:?}",
+                    &v[..v.len().min(16)]
+                );
+                Err(io::Error::new(kind, msg))
+            }
+        }
+    }
+
+    /// Find a stream's type without seeking.
+    ///
+    /// Reads up to 512 bytes (tar keeps its magic at offset 257) and hands
+    /// them back so the caller can chain them in front of the rest of the
+    /// stream. This works on pipes and sockets.
+    ///
+    /// If the stream's type is unknown this method will return `Err(InvalidInput)`
+    pub fn from_peek<R: Read>(r: &mut R) -> io::Result<(Format, Vec<u8>)> {
+        let mut v = Vec::with_capacity(512);
+        let _ = (&mut *r).take(512).read_to_end(&mut v)?;
+        match what_format(v.as_slice()) {
+            Option::Some(f) => Ok((f, v)),
+            Option::None => {
+                let kind = io::ErrorKind::InvalidInput;
+                let msg = format!(
+                    "Could not identify magic number {:?}",
+                    &v[..v.len().min(16)]
+                );
                 Err(io::Error::new(kind, msg))
             }
         }
@@ -274,14 +288,18 @@ impl Format {
  * SO YEAH IT IS RECURSIVE DECENT
  */
 fn what_format(x: &[u8]) -> Option<Format> {
-    match &x[0..2] {
+    // an archive with no members is nothing but zeros, or nothing at all
+    if x.iter().all(|b| *b == 0) {
+        return Some(Format::Tar(Quality::Default));
+    }
+    match head(x, 2) {
         b"\x1F\x9D" => return Some(Format::LZW(Quality::Default)),
         b"\x1F\xA0" => return Some(Format::LZH(Quality::Default)),
         b"\x1F\x8B" => return Some(Format::Gzip(Quality::Default)),
         b"\x30\x30" | b"\x20\x00" => return Some(Format::Tar(Quality::Default)),
         _ => {}
     };
-    match &x[0..3] {
+    match head(x, 3) {
         b"\x37\x7A\xBC" | b"\xAF\x27\x1C" => return Some(Format::Xz(Quality::Default)),
         b"\x42\x5A\x68" => return Some(Format::Bzip2(Quality::Default)),
         b"\x75\x73\x74" | b"\x61\x72\x20" | b"\x61\x72\x00" => {
@@ -289,7 +307,7 @@ fn what_format(x: &[u8]) -> Option<Format> {
         }
         _ => {}
     };
-    match &x[0..4] {
+    match head(x, 4) {
     b"\x81\xCF\xB2\xCE" |
     b"\xCE\xB2\xCF\x81" => return Some(Format::Brotli(Quality::Default)),
     b"\x18\x4D\x22\x04" |
@@ -302,13 +320,28 @@ fn what_format(x: &[u8]) -> Option<Format> {
     b"\xFD\x2F\xB5\x28" => return Some(Format::Zstd(Quality::Default)),
     _ => { }
   };
-    match &x[0..6] {
+    match head(x, 6) {
         b"\xFD\x37\x7A\x58\x5A\x00" => return Some(Format::Xz(Quality::Default)),
         _ => {}
     };
-    match &x[0..9] {
-        b"\xFF\x06\x00\x73\x4E\x61\x50\x70\x59" => return Some(Format::Snappy(Quality::Default)),
+    match head(x, 10) {
+        // stream identifier chunk: type 0xFF, 3 byte length 6, then `sNaPpY`
+        b"\xFF\x06\x00\x00\x73\x4E\x61\x50\x70\x59" => {
+            return Some(Format::Snappy(Quality::Default))
+        }
         _ => {}
     };
+    // POSIX/GNU tar keep their magic inside the first header block
+    if x.len() >= 263 {
+        match &x[257..262] {
+            b"ustar" => return Some(Format::Tar(Quality::Default)),
+            _ => {}
+        };
+    }
     None
 }
+
+/// The first `n` bytes, or nothing when the input is shorter
+fn head(x: &[u8], n: usize) -> &[u8] {
+    if x.len() < n { &[] } else { &x[..n] }
+}
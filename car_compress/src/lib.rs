@@ -31,6 +31,7 @@ pub mod libxz {
 extern crate flate2;
 pub mod libflate {
     pub use super::flate2::Compression as GzQuality;
+    pub use super::flate2::GzBuilder as Builder;
     pub use super::flate2::write::GzEncoder as Encode;
     pub use super::flate2::read::GzDecoder as Decode;
 }
@@ -32,8 +32,8 @@ fn main() {
         let reader = match arg.build_reader() {
             Ok(x) => x,
             Err(e) => {
-                println!("Could not construct reader");
-                println!("{:?}", e);
+                eprintln!("Could not construct reader");
+                eprintln!("{:?}", e);
                 ::std::process::exit(1);
             }
         };
@@ -41,29 +41,41 @@ fn main() {
         match arg.do_read(a) {
             Ok(_) => ::std::process::exit(0),
             Err(e) => {
-                println!("Encountered unrecoverable error");
-                println!("{:?}", e);
+                eprintln!("Encountered unrecoverable error");
+                eprintln!("{:?}", e);
                 ::std::process::exit(1);
             }
         };
-    } else {
-
+    } else if arg.is_standalone_action() {
+        match arg.do_standalone() {
+            Ok(_) => ::std::process::exit(0),
+            Err(e) => {
+                eprintln!("Encountered unrecoverable error");
+                eprintln!("{:?}", e);
+                ::std::process::exit(1);
+            }
+        };
+    } else if arg.is_write_action() {
         match arg.do_compress() {
             Ok(mut x) => {
                 match x.flush() {
                     Ok(_) => ::std::process::exit(0),
                     Err(e) => {
-                        println!("Compression succeeded, but flushing the file didn't");
-                        println!("{:?}", e);
+                        eprintln!("Compression succeeded, but flushing the file didn't");
+                        eprintln!("{:?}", e);
                         ::std::process::exit(1);
                     }
                 }
             }
             Err(e) => {
-                println!("Encountered unrecoverable error");
-                println!("{:?}", e);
+                eprintln!("Encountered unrecoverable error");
+                eprintln!("{:?}", e);
                 ::std::process::exit(1);
             }
         };
+    } else {
+        eprintln!("I didn't understand that");
+        eprintln!("Try running `--help`");
+        ::std::process::exit(1);
     }
 }